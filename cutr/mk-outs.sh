@@ -10,12 +10,12 @@ OUT_DIR="tests/expected"
 [[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
 rm -f $OUT_DIR/*
 
-for FLD in 1 2 3 1-2 2-3 1-3; do
+for FLD in 1 2 3 1-2 2-3 1-3 2- -2 3,1; do
     cut -f $FLD      $TSV > "$OUT_DIR/$(basename $TSV).f${FLD}.out"
     cut -f $FLD -d , $CSV > "$OUT_DIR/$(basename $CSV).f${FLD}.dcomma.out"
 done
 
-for POS in 1 2 8 1-2 2-3 1-8; do
+for POS in 1 2 8 1-2 2-3 1-8 8- -2 8,1-2; do
     cut -b $POS $TSV > "$OUT_DIR/$(basename $TSV).b${POS}.out"
     cut -b $POS $CSV > "$OUT_DIR/$(basename $CSV).b${POS}.out"
 done
@@ -25,4 +25,23 @@ for POS in 1 2 8 1-2 2-3 1-8; do
     cut -c $POS $CSV > "$OUT_DIR/$(basename $CSV).c${POS}.out"
 done
 
-echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
+cut -f 2 --complement $TSV > "$OUT_DIR/$(basename $TSV).f2.complement.out"
+cut -b 2-3 --complement $TSV > "$OUT_DIR/$(basename $TSV).b2-3.complement.out"
+cut -f 1,3 --output-delimiter " | " $TSV \
+    > "$OUT_DIR/$(basename $TSV).f1,3.odelim.out"
+cut -b 1-2,4 --output-delimiter "::" $TSV \
+    > "$OUT_DIR/$(basename $TSV).b1-2,4.odelim.out"
+
+for FLD in 1 2 3 1,3 2-; do
+    cut -f $FLD      $BOOKS > "$OUT_DIR/books.tsv.f${FLD}.raw.out"
+    cut -f $FLD -d , tests/inputs/books.csv \
+        > "$OUT_DIR/books.csv.f${FLD}.raw.out"
+    cut -f $FLD -d " " tests/inputs/books.txt \
+        > "$OUT_DIR/books.txt.f${FLD}.raw.out"
+done
+
+echo -e "A\nÉ\nS\nJ" > "$OUT_DIR/books.c1,1.out"
+echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.preserve.out"
+echo -e "Title\tAuthor\nLa Confession de Claude\tÉmile Zola
+Waiting for Godot\tSamuel Beckett
+20,000 Leagues Under the Sea\tJules Verne" > "$OUT_DIR/books.f3,1.preserve.out"
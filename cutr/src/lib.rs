@@ -1,14 +1,15 @@
 use anyhow::{anyhow, bail, Result};
-use clap::Parser;
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use clap::{Parser, ValueEnum};
+use csv::{ReaderBuilder, Terminator, WriterBuilder};
 use regex::Regex;
 use std::{
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Write},
     num::NonZeroUsize,
     ops::Range,
 };
-
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -18,9 +19,93 @@ struct Args {
     #[arg(default_value = "-")]
     files: Vec<String>,
 
-    /// Field delimiter
-    #[arg(short, long, value_name = "DELIMITER", default_value = "\t")]
-    delimiter: String,
+    /// Field delimiter, split on without CSV quoting when longer than a
+    /// byte [default: tab]
+    #[arg(short, long, value_name = "DELIMITER")]
+    delimiter: Option<String>,
+
+    /// How to read fields [default: from the delimiter]
+    #[arg(long, value_name = "FORMAT")]
+    input_format: Option<InputFormat>,
+
+    /// Split fields on matches of a regular expression
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        conflicts_with_all = ["delimiter", "input_format"]
+    )]
+    regex_delimiter: Option<String>,
+
+    /// Split fields on runs of whitespace, ignoring it at either end
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["delimiter", "regex_delimiter", "input_format"]
+    )]
+    whitespace: bool,
+
+    /// Split fields on the delimiter alone, without CSV quoting, as
+    /// --input-format raw does
+    #[arg(long, conflicts_with = "input_format")]
+    raw: bool,
+
+    /// Keep the selection in the order given, repeats included
+    #[arg(long)]
+    preserve_order: bool,
+
+    /// Select everything but the listed positions
+    #[arg(long, conflicts_with = "preserve_order")]
+    complement: bool,
+
+    /// Skip lines without the delimiter
+    #[arg(short = 's', long)]
+    only_delimited: bool,
+
+    /// Output delimiter [default: the field delimiter, a space after
+    /// splitting on a pattern or whitespace, nothing between bytes and
+    /// chars]
+    #[arg(long, value_name = "STRING")]
+    output_delimiter: Option<String>,
+
+    /// How to print fields, where jsonl and markdown take their keys from
+    /// the header row. A line without the delimiter is one field, so jsonl
+    /// puts it whole under the first key unless -s skips it [default: like
+    /// the input]
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        conflicts_with_all = ["output_delimiter", "bytes", "chars", "columns"]
+    )]
+    output_format: Option<OutputFormat>,
+
+    /// With -c, count grapheme clusters rather than chars
+    #[arg(long)]
+    graphemes: bool,
+
+    /// With -b, leave out the chars a range would split
+    #[arg(short = 'n')]
+    whole_chars: bool,
+
+    /// End records with NUL rather than newline, in and out
+    #[arg(short, long)]
+    zero_terminated: bool,
+
+    /// Print the header row, which --field-names otherwise leaves out, or
+    /// the names from --spec
+    #[arg(
+        long,
+        conflicts_with_all = ["fields", "bytes", "chars", "columns", "widths"]
+    )]
+    header: bool,
+
+    /// Match --field-names as regular expressions
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "fields", "bytes", "chars", "columns", "widths", "spec"
+        ]
+    )]
+    name_regex: bool,
 
     #[command(flatten)]
     extract: ArgsExtract,
@@ -30,16 +115,59 @@ struct Args {
 #[group(required = true, multiple = false)]
 struct ArgsExtract {
     /// Selected fields
-    #[arg(short, long, value_name = "FIELDS")]
+    #[arg(short, long, value_name = "FIELDS", allow_hyphen_values = true)]
     fields: Option<String>,
 
+    /// Selected fields, by the names in the first row
+    #[arg(short = 'F', long, value_name = "NAMES", allow_hyphen_values = true)]
+    field_names: Option<String>,
+
     /// Selected bytes
-    #[arg(short, long, value_name = "BYTES")]
+    #[arg(short, long, value_name = "BYTES", allow_hyphen_values = true)]
     bytes: Option<String>,
 
     /// Selected chars
-    #[arg(short, long, value_name = "CHARS")]
+    #[arg(short, long, value_name = "CHARS", allow_hyphen_values = true)]
     chars: Option<String>,
+
+    /// Selected display columns, where wide chars take two
+    #[arg(long, value_name = "COLUMNS", allow_hyphen_values = true)]
+    columns: Option<String>,
+
+    /// Fixed-width fields, by their widths in chars
+    #[arg(long, value_name = "WIDTHS")]
+    widths: Option<String>,
+
+    /// Fixed-width fields, by name and range of chars
+    #[arg(long, value_name = "NAME:START-END,...")]
+    spec: Option<String>,
+
+    /// Print each record through a template, where {N} is field N and
+    /// {N:upper} transforms it with upper, lower, trim or len
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = [
+            "output_delimiter", "output_format", "complement",
+            "preserve_order", "header", "name_regex"
+        ]
+    )]
+    template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    Csv,
+    Tsv,
+    Raw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Csv,
+    Tsv,
+    Jsonl,
+    Markdown,
 }
 
 type PositionList = Vec<Range<usize>>;
@@ -47,8 +175,336 @@ type PositionList = Vec<Range<usize>>;
 #[derive(Debug)]
 enum Extract {
     Fields(PositionList),
+    FieldNames(String),
     Bytes(PositionList),
     Chars(PositionList),
+    Columns(PositionList),
+    Fixed(Vec<String>, PositionList),
+    Template(Vec<Piece>),
+}
+
+/// A part of a --template
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Field(usize, Option<Transform>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transform {
+    Upper,
+    Lower,
+    Trim,
+    Len,
+}
+
+impl Transform {
+    // Bytes that aren't valid UTF-8 are left alone, and count as a char
+    fn apply(self, field: &[u8]) -> Vec<u8> {
+        let map_chunks = |map: fn(&str) -> String| {
+            field.utf8_chunks().fold(vec![], |mut mapped, chunk| {
+                mapped.extend(map(chunk.valid()).into_bytes());
+                mapped.extend(chunk.invalid());
+                mapped
+            })
+        };
+        match self {
+            Transform::Upper => map_chunks(str::to_uppercase),
+            Transform::Lower => map_chunks(str::to_lowercase),
+            Transform::Trim => trim(field).to_vec(),
+            Transform::Len => split_chars(field).len().to_string().into_bytes(),
+        }
+    }
+}
+
+impl Args {
+    fn terminator(&self) -> u8 {
+        if self.zero_terminated {
+            b'\0'
+        } else {
+            b'\n'
+        }
+    }
+
+    fn delimiter(&self) -> &str {
+        self.delimiter.as_deref().unwrap_or("\t")
+    }
+
+    // The input format asked for, if any
+    fn input_format(&self) -> Option<InputFormat> {
+        if self.raw {
+            Some(InputFormat::Raw)
+        } else {
+            self.input_format
+        }
+    }
+
+    // Put positions in the order the selection options ask for
+    fn arrange(&self, pos: PositionList) -> PositionList {
+        if self.complement {
+            complement_pos(sort_pos(pos))
+        } else if self.preserve_order {
+            pos
+        } else {
+            sort_pos(pos)
+        }
+    }
+}
+
+/// How lines are split into fields
+#[derive(Debug)]
+enum Splitter {
+    Csv(u8),
+    Str(String),
+    Regex(regex::bytes::Regex),
+    Whitespace,
+}
+
+impl Splitter {
+    fn new(args: &Args) -> Result<Self> {
+        if args.whitespace {
+            return Ok(Splitter::Whitespace);
+        }
+        if let Some(pattern) = &args.regex_delimiter {
+            return Ok(Splitter::Regex(
+                regex::bytes::Regex::new(pattern).map_err(|_| {
+                    anyhow!(r#"invalid --regex-delimiter "{pattern}""#)
+                })?,
+            ));
+        }
+
+        let delimiter = args.delimiter();
+        Ok(match args.input_format() {
+            Some(InputFormat::Raw) => Splitter::Str(delimiter.to_string()),
+            Some(format) => {
+                let default = match format {
+                    InputFormat::Csv => b',',
+                    _ => b'\t',
+                };
+                match args.delimiter.as_deref().map(str::as_bytes) {
+                    None => Splitter::Csv(default),
+                    Some([delimiter]) => Splitter::Csv(*delimiter),
+                    Some(_) => bail!(
+                        r#"--delim "{delimiter}" must be a single byte for {}"#,
+                        format.to_possible_value().unwrap().get_name()
+                    ),
+                }
+            }
+            None if delimiter.len() == 1 => {
+                Splitter::Csv(delimiter.as_bytes()[0])
+            }
+            None => Splitter::Str(delimiter.to_string()),
+        })
+    }
+
+    // The fields of a line, without CSV quoting
+    fn split(&self, line: &[u8]) -> Vec<Vec<u8>> {
+        match self {
+            Splitter::Csv(delimiter) => line
+                .split(|byte| byte == delimiter)
+                .map(<[u8]>::to_vec)
+                .collect(),
+            Splitter::Str(delimiter) => {
+                let delimiter = delimiter.as_bytes();
+                let mut fields = vec![];
+                let mut start = 0;
+                let mut i = 0;
+                while i + delimiter.len() <= line.len() {
+                    if line[i..].starts_with(delimiter) {
+                        fields.push(line[start..i].to_vec());
+                        i += delimiter.len();
+                        start = i;
+                    } else {
+                        i += 1;
+                    }
+                }
+                fields.push(line[start..].to_vec());
+                fields
+            }
+            Splitter::Regex(re) => re.split(line).map(<[u8]>::to_vec).collect(),
+            Splitter::Whitespace => {
+                let mut fields = vec![];
+                let mut field = vec![];
+                for c in split_chars(line) {
+                    if !is_whitespace(c) {
+                        field.extend_from_slice(c);
+                    } else if !field.is_empty() {
+                        fields.push(std::mem::take(&mut field));
+                    }
+                }
+                if !field.is_empty() {
+                    fields.push(field);
+                }
+                fields
+            }
+        }
+    }
+
+    // The records of a file
+    fn records<'a>(
+        &'a self,
+        file: Box<dyn BufRead>,
+        terminator: u8,
+    ) -> Box<dyn Iterator<Item = Result<Vec<Vec<u8>>>> + 'a> {
+        match self {
+            Splitter::Csv(delimiter) => {
+                let reader = ReaderBuilder::new()
+                    .delimiter(*delimiter)
+                    .terminator(match terminator {
+                        b'\n' => Terminator::CRLF,
+                        _ => Terminator::Any(terminator),
+                    })
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(file);
+                Box::new(reader.into_byte_records().map(|record| {
+                    Ok(record?.iter().map(<[u8]>::to_vec).collect())
+                }))
+            }
+            _ => Box::new(
+                read_records(file, terminator)
+                    .map(|record| Ok(self.split(&record?))),
+            ),
+        }
+    }
+}
+
+/// How selected fields are printed
+enum FieldFormat {
+    Csv(Box<csv::Writer<io::Stdout>>),
+    Joined(String),
+    // The keys from the header. JSON has to be UTF-8, so invalid bytes in
+    // keys and values become U+FFFD.
+    Jsonl(Vec<String>),
+    Markdown,
+}
+
+/// Where selected fields go
+struct FieldWriter {
+    format: FieldFormat,
+    terminator: u8,
+}
+
+impl FieldWriter {
+    fn new(args: &Args, splitter: &Splitter) -> Self {
+        let terminator = args.terminator();
+        let csv = |delimiter| {
+            FieldFormat::Csv(Box::new(
+                WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .terminator(Terminator::Any(terminator))
+                    .flexible(true)
+                    .from_writer(io::stdout()),
+            ))
+        };
+        let format = match (args.output_format, &args.output_delimiter) {
+            (Some(OutputFormat::Csv), _) => csv(b','),
+            (Some(OutputFormat::Tsv), _) => csv(b'\t'),
+            (Some(OutputFormat::Jsonl), _) => FieldFormat::Jsonl(vec![]),
+            (Some(OutputFormat::Markdown), _) => FieldFormat::Markdown,
+            (None, Some(out_delim)) => FieldFormat::Joined(out_delim.clone()),
+            (None, None) => match splitter {
+                Splitter::Csv(delimiter) => csv(*delimiter),
+                Splitter::Str(delimiter) => {
+                    FieldFormat::Joined(delimiter.clone())
+                }
+                Splitter::Regex(_) | Splitter::Whitespace => {
+                    FieldFormat::Joined(" ".to_string())
+                }
+            },
+        };
+        FieldWriter { format, terminator }
+    }
+
+    // Whether the first record has to be read as the header
+    fn needs_header(&self) -> bool {
+        matches!(self.format, FieldFormat::Jsonl(_) | FieldFormat::Markdown)
+    }
+
+    fn header(&mut self, fields: &[&[u8]]) -> Result<()> {
+        match &mut self.format {
+            FieldFormat::Jsonl(keys) => {
+                *keys = fields
+                    .iter()
+                    .map(|key| String::from_utf8_lossy(key).into_owned())
+                    .collect();
+            }
+            FieldFormat::Markdown => {
+                self.write(fields)?;
+                let rule = format!("|{}", " --- |".repeat(fields.len()));
+                write_record(rule.as_bytes(), self.terminator)?;
+            }
+            _ => self.write(fields)?,
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, fields: &[&[u8]]) -> Result<()> {
+        let line = match &mut self.format {
+            // The csv writer quotes an empty record as `""`, where cut
+            // prints an empty line
+            FieldFormat::Csv(wtr) if matches!(fields, [] | [[]]) => {
+                wtr.flush()?;
+                vec![]
+            }
+            FieldFormat::Csv(wtr) => {
+                wtr.write_record(fields)?;
+                return Ok(());
+            }
+            FieldFormat::Joined(out_delim) => fields.join(out_delim.as_bytes()),
+            FieldFormat::Jsonl(keys) => {
+                let object: serde_json::Map<_, _> = keys
+                    .iter()
+                    .zip(fields)
+                    .map(|(key, value)| {
+                        (key.clone(), String::from_utf8_lossy(value).into())
+                    })
+                    .collect();
+                serde_json::Value::Object(object).to_string().into_bytes()
+            }
+            FieldFormat::Markdown => {
+                let cells: Vec<_> = fields
+                    .iter()
+                    .map(|cell| {
+                        cell.iter().fold(vec![], |mut cell, &byte| {
+                            if byte == b'|' {
+                                cell.push(b'\\');
+                            }
+                            cell.push(byte);
+                            cell
+                        })
+                    })
+                    .collect();
+                [b"| ", cells.join(&b" | "[..]).as_slice(), b" |"].concat()
+            }
+        };
+        write_record(&line, self.terminator)?;
+        Ok(())
+    }
+}
+
+// --------------------------------------------------
+// Print a record followed by its terminator
+fn write_record(record: &[u8], terminator: u8) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(record)?;
+    stdout.write_all(&[terminator])
+}
+
+// --------------------------------------------------
+// The records of a file as bytes, without their terminators or the
+// carriage return before a newline
+fn read_records(
+    file: Box<dyn BufRead>,
+    terminator: u8,
+) -> impl Iterator<Item = Result<Vec<u8>>> {
+    file.split(terminator).map(move |record| {
+        let mut record = record?;
+        if terminator == b'\n' && record.last() == Some(&b'\r') {
+            record.pop();
+        }
+        Ok(record)
+    })
 }
 
 fn open(filename: &str) -> Result<Box<dyn BufRead>> {
@@ -61,56 +517,97 @@ fn open(filename: &str) -> Result<Box<dyn BufRead>> {
 pub fn run() -> Result<()> {
     let args = Args::parse();
 
-    let delim_bytes = args.delimiter.as_bytes();
-    if delim_bytes.len() != 1 {
-        bail!(r#"--delim "{}" must be a single byte"#, args.delimiter);
+    if args.delimiter().is_empty() {
+        bail!(r#"--delim "" must not be empty"#);
     }
-    let delimiter: u8 = *delim_bytes.first().unwrap();
+    let splitter = Splitter::new(&args)?;
 
+    if args.only_delimited
+        && args.extract.fields.is_none()
+        && args.extract.field_names.is_none()
+        && args.extract.template.is_none()
+    {
+        bail!(
+            "suppressing non-delimited lines makes sense \
+            only when operating on fields"
+        );
+    }
+
+    let parse =
+        |list: &String| parse_pos(list.clone()).map(|pos| args.arrange(pos));
+    let out_delim = args.output_delimiter.as_deref();
     let extract = if let Some(fields) =
-        args.extract.fields.map(parse_pos).transpose()?
+        args.extract.fields.as_ref().map(parse).transpose()?
     {
         Extract::Fields(fields)
+    } else if let Some(names) = &args.extract.field_names {
+        Extract::FieldNames(names.clone())
     } else if let Some(bytes) =
-        args.extract.bytes.map(parse_pos).transpose()?
+        args.extract.bytes.as_ref().map(parse).transpose()?
     {
         Extract::Bytes(bytes)
     } else if let Some(chars) =
-        args.extract.chars.map(parse_pos).transpose()?
+        args.extract.chars.as_ref().map(parse).transpose()?
     {
         Extract::Chars(chars)
+    } else if let Some(columns) =
+        args.extract.columns.as_ref().map(parse).transpose()?
+    {
+        Extract::Columns(columns)
+    } else if let Some(widths) = &args.extract.widths {
+        Extract::Fixed(vec![], parse_widths(widths)?)
+    } else if let Some(spec) = &args.extract.spec {
+        let (names, ranges) = parse_spec(spec)?;
+        Extract::Fixed(names, ranges)
+    } else if let Some(template) = &args.extract.template {
+        Extract::Template(parse_template(template)?)
     } else {
-        unreachable!("Must have --fields, --bytes, or --chars");
+        unreachable!("Must have a selection");
     };
 
     for filename in &args.files {
         match open(filename) {
             Err(err) => eprintln!("{filename}: {err}"),
             Ok(file) => match &extract {
-                Extract::Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-
-                    let mut wtr = WriterBuilder::new()
-                        .delimiter(delimiter)
-                        .from_writer(io::stdout());
-
-                    for record in reader.records() {
-                        wtr.write_record(extract_fields(
-                            &record?, field_pos,
-                        ))?;
-                    }
+                Extract::Fields(_) | Extract::FieldNames(_) => {
+                    cut_fields(file, &args, &splitter, &extract)?
+                }
+                Extract::Fixed(names, ranges) => {
+                    cut_fixed(file, &args, &splitter, names, ranges)?
+                }
+                Extract::Template(pieces) => {
+                    cut_template(file, &args, &splitter, pieces)?
                 }
                 Extract::Bytes(byte_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                    let out_delim = out_delim.unwrap_or_default();
+                    for record in read_records(file, args.terminator()) {
+                        let selected = extract_bytes(
+                            &record?,
+                            byte_pos,
+                            out_delim,
+                            args.whole_chars,
+                        );
+                        write_record(&selected, args.terminator())?;
                     }
                 }
                 Extract::Chars(char_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                    let out_delim = out_delim.unwrap_or_default();
+                    for record in read_records(file, args.terminator()) {
+                        let record = record?;
+                        let selected = if args.graphemes {
+                            extract_graphemes(&record, char_pos, out_delim)
+                        } else {
+                            extract_chars(&record, char_pos, out_delim)
+                        };
+                        write_record(&selected, args.terminator())?;
+                    }
+                }
+                Extract::Columns(col_pos) => {
+                    let out_delim = out_delim.unwrap_or_default();
+                    for record in read_records(file, args.terminator()) {
+                        let selected =
+                            extract_columns(&record?, col_pos, out_delim);
+                        write_record(&selected, args.terminator())?;
                     }
                 }
             },
@@ -118,9 +615,112 @@ pub fn run() -> Result<()> {
     }
 
     Ok(())
+}
 
-    println!("{:#?}", args);
+// --------------------------------------------------
+// Print the selected fields of each record. Selecting by name takes the
+// positions from the first record, which is printed only with --header or
+// as the keys of an output format that needs them.
+fn cut_fields(
+    file: Box<dyn BufRead>,
+    args: &Args,
+    splitter: &Splitter,
+    extract: &Extract,
+) -> Result<()> {
+    let mut out = FieldWriter::new(args, splitter);
+    let mut records = splitter.records(file, args.terminator());
+    let names = match extract {
+        Extract::FieldNames(names) => Some(names),
+        _ => None,
+    };
+    let header = if names.is_some() || out.needs_header() {
+        match records.next().transpose()? {
+            Some(header) => header,
+            None => return Ok(()),
+        }
+    } else {
+        vec![]
+    };
+    let header: Vec<_> = header.iter().map(Vec::as_slice).collect();
+    let field_pos = match (extract, names) {
+        (Extract::Fields(field_pos), _) => field_pos.clone(),
+        (_, Some(names)) => {
+            let header: Vec<_> = header
+                .iter()
+                .map(|name| String::from_utf8_lossy(name))
+                .collect();
+            let header: Vec<_> = header.iter().map(AsRef::as_ref).collect();
+            args.arrange(find_names(&header, names, args.name_regex)?)
+        }
+        _ => unreachable!("Must be --fields or --field-names"),
+    };
+    if args.header || out.needs_header() {
+        out.header(&extract_fields(&header, &field_pos))?;
+    }
 
+    for record in records {
+        let record = record?;
+        let fields: Vec<_> = record.iter().map(Vec::as_slice).collect();
+        // Lines without the delimiter pass through whole, which jsonl
+        // writes under the first key
+        if fields.len() < 2 {
+            if !args.only_delimited {
+                out.write(&fields)?;
+            }
+            continue;
+        }
+        out.write(&extract_fields(&fields, &field_pos))?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+// Print each record through the template. Records without the delimiter
+// are one field, and -s skips them.
+fn cut_template(
+    file: Box<dyn BufRead>,
+    args: &Args,
+    splitter: &Splitter,
+    pieces: &[Piece],
+) -> Result<()> {
+    for record in splitter.records(file, args.terminator()) {
+        let record = record?;
+        if record.len() < 2 && args.only_delimited {
+            continue;
+        }
+        let fields: Vec<_> = record.iter().map(Vec::as_slice).collect();
+        let line = fill_template(&fields, pieces);
+        write_record(&line, args.terminator())?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+// Print the fixed-width fields of each line. The names from --spec make
+// the header, else the fields of the first line do when the output format
+// needs one.
+fn cut_fixed(
+    file: Box<dyn BufRead>,
+    args: &Args,
+    splitter: &Splitter,
+    names: &[String],
+    ranges: &[Range<usize>],
+) -> Result<()> {
+    let mut out = FieldWriter::new(args, splitter);
+    let mut lines = read_records(file, args.terminator());
+    if !names.is_empty() && (args.header || out.needs_header()) {
+        let names: Vec<_> = names.iter().map(String::as_bytes).collect();
+        out.header(&names)?;
+    } else if names.is_empty() && out.needs_header() {
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        out.header(&extract_fixed(&line, ranges))?;
+    }
+
+    for line in lines {
+        out.write(&extract_fixed(&line?, ranges))?;
+    }
     Ok(())
 }
 
@@ -130,6 +730,7 @@ pub fn run() -> Result<()> {
 // Ensures the number does not start with '+'.
 // Returns an index, which is a non-negative integer that is
 // one less than the number represented by the original input.
+#[allow(clippy::obfuscated_if_else)]
 fn parse_index(input: &str) -> Result<usize> {
     let value_error = || anyhow!(r#"illegal list value: "{input}""#);
     input
@@ -144,16 +745,29 @@ fn parse_index(input: &str) -> Result<usize> {
 }
 
 // --------------------------------------------------
+// Parse a comma-separated list of positions and ranges. A range may leave
+// out its start, meaning the first position, or its end, meaning through
+// the end of the line, which is kept as `usize::MAX`.
 fn parse_pos(range: String) -> Result<PositionList> {
-    let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
+    let range_re = Regex::new(r"^(\d*)-(\d*)$").unwrap();
     range
         .split(',')
         .map(|val| {
             parse_index(val).map(|n| n..n + 1).or_else(|e| {
                 range_re.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
-                    if n1 >= n2 {
+                    let (start, end) = (&captures[1], &captures[2]);
+                    if start.is_empty() && end.is_empty() {
+                        bail!(r#"illegal list value: "{val}""#);
+                    }
+                    let n1 = match start {
+                        "" => 0,
+                        _ => parse_index(start)?,
+                    };
+                    if end.is_empty() {
+                        return Ok(n1..usize::MAX);
+                    }
+                    let n2 = parse_index(end)?;
+                    if n1 >= n2 && !start.is_empty() {
                         bail!(
                             "First number in range ({}) \
                             must be lower than second number ({})",
@@ -165,50 +779,441 @@ fn parse_pos(range: String) -> Result<PositionList> {
                 })
             })
         })
-        .collect::<Result<_, _>>()
-        .map_err(From::from)
+        .collect()
+}
+
+// --------------------------------------------------
+// Parse a comma-separated list of field widths into the ranges of chars
+// they take up one after the other.
+fn parse_widths(widths: &str) -> Result<PositionList> {
+    let mut start: usize = 0;
+    widths
+        .split(',')
+        .map(|width| {
+            let end = start
+                .checked_add(parse_index(width)? + 1)
+                .ok_or_else(|| anyhow!(r#"illegal list value: "{width}""#))?;
+            Ok(std::mem::replace(&mut start, end)..end)
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+// Parse a comma-separated list of `name:start-end` fields, where the range
+// takes the same forms as a single one for -c.
+fn parse_spec(spec: &str) -> Result<(Vec<String>, PositionList)> {
+    spec.split(',')
+        .map(|field| {
+            let spec_error = || anyhow!(r#"invalid field spec: "{field}""#);
+            let (name, range) = field.split_once(':').ok_or_else(spec_error)?;
+            if name.is_empty() {
+                return Err(spec_error());
+            }
+            match parse_pos(range.to_string())?.as_slice() {
+                [range] => Ok((name.to_string(), range.clone())),
+                _ => Err(spec_error()),
+            }
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+// Parse a template of literal text and `{N}` or `{N:transform}` fields.
+// `{{` and `}}` stand for braces, and `\t`, `\n` and `\\` for a tab, a
+// newline and a backslash.
+fn parse_template(template: &str) -> Result<Vec<Piece>> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                text.push(c);
+            }
+            ('\\', Some(&escaped @ ('t' | 'n' | '\\'))) => {
+                chars.next();
+                text.push(match escaped {
+                    't' => '\t',
+                    'n' => '\n',
+                    _ => '\\',
+                });
+            }
+            ('}', _) => bail!(r#"unmatched "}}" in template"#),
+            ('{', _) => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => bail!(r#"unclosed "{{" in template"#),
+                    }
+                }
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(parse_template_field(&field)?);
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+// --------------------------------------------------
+// Parse the inside of a template field, a field number and an optional
+// transform after a colon.
+fn parse_template_field(field: &str) -> Result<Piece> {
+    let (index, transform) = match field.split_once(':') {
+        Some((index, transform)) => (index, Some(transform)),
+        None => (field, None),
+    };
+    let index = parse_index(index)
+        .map_err(|_| anyhow!(r#"invalid template field: "{{{field}}}""#))?;
+    let transform = transform
+        .map(|name| match name {
+            "upper" => Ok(Transform::Upper),
+            "lower" => Ok(Transform::Lower),
+            "trim" => Ok(Transform::Trim),
+            "len" => Ok(Transform::Len),
+            _ => Err(anyhow!(r#"unknown template transform: "{name}""#)),
+        })
+        .transpose()?;
+    Ok(Piece::Field(index, transform))
+}
+
+// --------------------------------------------------
+// Fill in a template from the fields of a record, where missing fields are
+// empty.
+fn fill_template(fields: &[&[u8]], pieces: &[Piece]) -> Vec<u8> {
+    pieces
+        .iter()
+        .flat_map(|piece| match piece {
+            Piece::Text(text) => text.as_bytes().to_vec(),
+            Piece::Field(index, transform) => {
+                let field = fields.get(*index).copied().unwrap_or_default();
+                match transform {
+                    Some(transform) => transform.apply(field),
+                    None => field.to_vec(),
+                }
+            }
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+// Find a comma-separated list of names among the fields of a header. Names
+// match exactly, and `first-last` selects the fields between two names,
+// either of which may be left out as with positions. As regular
+// expressions, each one selects every field it matches instead.
+fn find_names(
+    header: &[&str],
+    names: &str,
+    regex: bool,
+) -> Result<PositionList> {
+    let find = |name: &str| {
+        header
+            .iter()
+            .position(|field| *field == name)
+            .ok_or_else(|| anyhow!(r#"unknown field name: "{name}""#))
+    };
+    let mut pos = vec![];
+    for name in names.split(',') {
+        if regex {
+            let re = Regex::new(name)
+                .map_err(|_| anyhow!(r#"invalid field pattern: "{name}""#))?;
+            let start = pos.len();
+            pos.extend(
+                (0..header.len())
+                    .filter(|&i| re.is_match(header[i]))
+                    .map(|i| i..i + 1),
+            );
+            if pos.len() == start {
+                bail!(r#"no field name matches "{name}""#);
+            }
+        } else if let Ok(i) = find(name) {
+            pos.push(i..i + 1);
+        } else if let Some((first, last)) =
+            name.split_once('-').filter(|_| name != "-")
+        {
+            let start = match first {
+                "" => 0,
+                _ => find(first)?,
+            };
+            let end = match last {
+                "" => usize::MAX,
+                _ => find(last)? + 1,
+            };
+            if start >= end {
+                bail!(r#"field "{first}" must come before "{last}""#);
+            }
+            pos.push(start..end);
+        } else {
+            bail!(r#"unknown field name: "{name}""#);
+        }
+    }
+    Ok(pos)
+}
+
+// --------------------------------------------------
+// Put positions in ascending order and merge the ranges that overlap, so
+// each position is selected once, as GNU cut does. Ranges that only touch
+// stay apart, as an output delimiter goes between them.
+fn sort_pos(mut pos: PositionList) -> PositionList {
+    pos.sort_by_key(|range| range.start);
+    let mut sorted: PositionList = vec![];
+    for range in pos {
+        match sorted.last_mut() {
+            Some(last) if range.start < last.end => {
+                last.end = last.end.max(range.end);
+            }
+            _ => sorted.push(range),
+        }
+    }
+    sorted
 }
 
 // --------------------------------------------------
-fn extract_fields<'a>(
-    record: &'a StringRecord,
+// The gaps between sorted positions, through the end of the line.
+fn complement_pos(pos: PositionList) -> PositionList {
+    let mut gaps = vec![];
+    let mut start = 0;
+    for range in pos {
+        if range.start > start {
+            gaps.push(start..range.start);
+        }
+        start = start.max(range.end);
+    }
+    if start < usize::MAX {
+        gaps.push(start..usize::MAX);
+    }
+    gaps
+}
+
+// --------------------------------------------------
+// Iterate the indexes of a range that fall below `len`, as ranges left
+// open at the end run to `usize::MAX`.
+fn clamp(range: &Range<usize>, len: usize) -> Range<usize> {
+    range.start.min(len)..range.end.min(len)
+}
+
+// --------------------------------------------------
+fn extract_fields<'a, T: ?Sized>(
+    fields: &[&'a T],
     field_pos: &[Range<usize>],
-) -> Vec<&'a str> {
+) -> Vec<&'a T> {
     field_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
+        .flat_map(|range| &fields[clamp(range, fields.len())])
+        .copied()
         .collect()
 }
 
 // --------------------------------------------------
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
+// The selected bytes, with `out_delim` between the ranges that select any.
+// With `whole_chars`, each range shrinks to leave out the chars it only
+// partly covers.
+fn extract_bytes(
+    line: &[u8],
+    byte_pos: &[Range<usize>],
+    out_delim: &str,
+    whole_chars: bool,
+) -> Vec<u8> {
+    // A UTF-8 continuation byte is never the start of a char
+    let is_boundary = |index: usize| {
+        line.get(index)
+            .is_none_or(|byte| byte & 0b1100_0000 != 0b1000_0000)
+    };
     let selected: Vec<_> = byte_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
+        .map(|range| {
+            let mut range = clamp(range, line.len());
+            if whole_chars {
+                while range.start < range.end && !is_boundary(range.start) {
+                    range.start += 1;
+                }
+                while range.start < range.end && !is_boundary(range.end) {
+                    range.end -= 1;
+                }
+            }
+            line.get(range).unwrap_or_default()
+        })
+        .filter(|piece| !piece.is_empty())
         .collect();
-    String::from_utf8_lossy(&selected).into_owned()
+    selected.join(out_delim.as_bytes())
+}
+
+// --------------------------------------------------
+// The chars of a line, each as the bytes that encode it, where a byte that
+// isn't valid UTF-8 stands on its own
+fn split_chars(line: &[u8]) -> Vec<&[u8]> {
+    let mut chars = vec![];
+    for chunk in line.utf8_chunks() {
+        let valid = chunk.valid();
+        chars.extend(
+            valid
+                .char_indices()
+                .map(|(i, c)| &valid.as_bytes()[i..i + c.len_utf8()]),
+        );
+        chars.extend(chunk.invalid().chunks(1));
+    }
+    chars
+}
+
+// --------------------------------------------------
+// The grapheme clusters of a line as bytes, where a byte that isn't valid
+// UTF-8 stands on its own
+fn split_graphemes(line: &[u8]) -> Vec<&[u8]> {
+    let mut graphemes = vec![];
+    for chunk in line.utf8_chunks() {
+        graphemes.extend(chunk.valid().graphemes(true).map(str::as_bytes));
+        graphemes.extend(chunk.invalid().chunks(1));
+    }
+    graphemes
 }
 
 // --------------------------------------------------
-fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
-    let chars: Vec<_> = line.chars().collect();
-    char_pos
+fn is_whitespace(c: &[u8]) -> bool {
+    std::str::from_utf8(c).is_ok_and(|c| c.chars().all(char::is_whitespace))
+}
+
+// --------------------------------------------------
+// A field without whitespace at either end
+fn trim(field: &[u8]) -> &[u8] {
+    let chars = split_chars(field);
+    let width = |c: &&[u8]| c.len();
+    let start = chars
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| chars.get(i)))
+        .take_while(|c| is_whitespace(c))
+        .map(width)
+        .sum();
+    let end = field.len()
+        - chars
+            .iter()
+            .rev()
+            .take_while(|c| is_whitespace(c))
+            .map(width)
+            .sum::<usize>();
+    &field[start..end.max(start)]
+}
+
+// --------------------------------------------------
+// The selected pieces of a line, with `out_delim` between the ranges that
+// select any
+fn join_pieces(
+    pieces: &[&[u8]],
+    pos: &[Range<usize>],
+    out_delim: &str,
+) -> Vec<u8> {
+    let selected: Vec<_> = pos
+        .iter()
+        .map(|range| pieces[clamp(range, pieces.len())].concat())
+        .filter(|piece| !piece.is_empty())
+        .collect();
+    selected.join(out_delim.as_bytes())
+}
+
+// --------------------------------------------------
+// The selected chars, with `out_delim` between the ranges that select any.
+fn extract_chars(
+    line: &[u8],
+    char_pos: &[Range<usize>],
+    out_delim: &str,
+) -> Vec<u8> {
+    join_pieces(&split_chars(line), char_pos, out_delim)
+}
+
+// --------------------------------------------------
+// Slice a line into fixed-width fields without their padding
+fn extract_fixed<'a>(line: &'a [u8], ranges: &[Range<usize>]) -> Vec<&'a [u8]> {
+    let chars = split_chars(line);
+    ranges
+        .iter()
+        .map(|range| {
+            let range = clamp(range, chars.len());
+            let start: usize =
+                chars[..range.start].iter().map(|c| c.len()).sum();
+            let len: usize = chars[range].iter().map(|c| c.len()).sum();
+            trim(&line[start..start + len])
+        })
         .collect()
 }
 
+// --------------------------------------------------
+// The selected grapheme clusters, with `out_delim` between the ranges that
+// select any.
+fn extract_graphemes(
+    line: &[u8],
+    grapheme_pos: &[Range<usize>],
+    out_delim: &str,
+) -> Vec<u8> {
+    join_pieces(&split_graphemes(line), grapheme_pos, out_delim)
+}
+
+// --------------------------------------------------
+// The grapheme clusters that fit within the selected display columns, with
+// `out_delim` between the ranges that select any. A cluster of no width
+// still takes up its column here, so it can be selected, as does a byte
+// that isn't valid UTF-8.
+fn extract_columns(
+    line: &[u8],
+    col_pos: &[Range<usize>],
+    out_delim: &str,
+) -> Vec<u8> {
+    let mut col = 0;
+    let cells: Vec<_> = split_graphemes(line)
+        .into_iter()
+        .map(|grapheme| {
+            let width = std::str::from_utf8(grapheme).map_or(1, |g| g.width());
+            let cell = (col..col + width.max(1), grapheme);
+            col = cell.0.end;
+            cell
+        })
+        .collect();
+    let selected: Vec<_> = col_pos
+        .iter()
+        .map(|range| {
+            cells
+                .iter()
+                .filter(|(cols, _)| {
+                    range.start <= cols.start && cols.end <= range.end
+                })
+                .map(|(_, grapheme)| *grapheme)
+                .collect::<Vec<_>>()
+                .concat()
+        })
+        .filter(|piece| !piece.is_empty())
+        .collect();
+    selected.join(out_delim.as_bytes())
+}
 
 // --------------------------------------------------
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
-    use super::{extract_bytes, extract_chars, extract_fields, parse_pos};
-    use csv::StringRecord;
+    use super::{
+        complement_pos, extract_bytes, extract_chars, extract_columns,
+        extract_fields, extract_fixed, extract_graphemes, fill_template,
+        find_names, parse_pos, parse_spec, parse_template, parse_widths,
+        sort_pos, split_chars, trim, Piece, Splitter, Transform,
+    };
     use pretty_assertions::assert_eq;
+    use regex::bytes::Regex;
+    use std::ops::Range;
+
+    type Extract = fn(&[u8], &[Range<usize>], &str) -> Vec<u8>;
+
+    // Run a function on the bytes of a valid UTF-8 line
+    fn on_str(
+        extract: Extract,
+    ) -> impl Fn(&str, &[Range<usize>], &str) -> String {
+        move |line, pos, out_delim| {
+            String::from_utf8(extract(line.as_bytes(), pos, out_delim)).unwrap()
+        }
+    }
 
     #[test]
     fn test_parse_pos() {
@@ -291,7 +1296,7 @@ mod unit_tests {
         let res = parse_pos("1,".to_string());
         assert!(res.is_err());
 
-        let res = parse_pos("1-".to_string());
+        let res = parse_pos("--1".to_string());
         assert!(res.is_err());
 
         let res = parse_pos("1-1-1".to_string());
@@ -347,11 +1352,234 @@ mod unit_tests {
         let res = parse_pos("15,19-20".to_string());
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+
+        // Open ranges
+        let res = parse_pos("3-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![2..usize::MAX]);
+
+        let res = parse_pos("-5".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..5]);
+
+        let res = parse_pos("-1,4-".to_string());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), vec![0..1, 3..usize::MAX]);
+
+        let res = parse_pos("-0".to_string());
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"illegal list value: "0""#
+        );
+    }
+
+    #[test]
+    fn test_parse_widths() {
+        assert_eq!(parse_widths("3").unwrap(), vec![0..3]);
+        assert_eq!(
+            parse_widths("10,5,20").unwrap(),
+            vec![0..10, 10..15, 15..35]
+        );
+        for bad in ["", "0", "1,", "a", "-1", "1-2"] {
+            assert!(parse_widths(bad).is_err(), "{bad}");
+        }
+        // The widths must add up to a position
+        assert_eq!(
+            parse_widths("18446744073709551615,1")
+                .unwrap_err()
+                .to_string(),
+            r#"illegal list value: "1""#
+        );
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let (names, ranges) = parse_spec("id:1-4,name:6-,flag:5").unwrap();
+        assert_eq!(names, ["id", "name", "flag"]);
+        assert_eq!(ranges, vec![0..4, 5..usize::MAX, 4..5]);
+
+        for (bad, field) in [
+            ("id", "id"),
+            (":1-2", ":1-2"),
+            ("id:1-2,", ""),
+            ("a:1,2", "2"),
+        ] {
+            assert_eq!(
+                parse_spec(bad).unwrap_err().to_string(),
+                format!(r#"invalid field spec: "{field}""#)
+            );
+        }
+        assert_eq!(
+            parse_spec("id:2-1").unwrap_err().to_string(),
+            "First number in range (2) must be lower than second number (1)"
+        );
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            parse_template(r"{3}\t{1}:{2:upper}").unwrap(),
+            vec![
+                Piece::Field(2, None),
+                Piece::Text("\t".to_string()),
+                Piece::Field(0, None),
+                Piece::Text(":".to_string()),
+                Piece::Field(1, Some(Transform::Upper)),
+            ]
+        );
+        assert_eq!(
+            parse_template(r"{{x}}\n\\\q").unwrap(),
+            vec![Piece::Text("{x}\n\\\\q".to_string())]
+        );
+        assert_eq!(parse_template("").unwrap(), vec![]);
+
+        for (bad, err) in [
+            ("{1", r#"unclosed "{" in template"#),
+            ("1}", r#"unmatched "}" in template"#),
+            ("{0}", r#"invalid template field: "{0}""#),
+            ("{a:len}", r#"invalid template field: "{a:len}""#),
+            ("{}", r#"invalid template field: "{}""#),
+            ("{1:title}", r#"unknown template transform: "title""#),
+        ] {
+            assert_eq!(parse_template(bad).unwrap_err().to_string(), err);
+        }
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let fill_template = |fields: &[&str], pieces| {
+            let fields: Vec<_> = fields.iter().map(|f| f.as_bytes()).collect();
+            String::from_utf8(fill_template(&fields, pieces)).unwrap()
+        };
+        let pieces = parse_template("{2:lower}-{1:trim}-{3:len}-{9}").unwrap();
+        assert_eq!(fill_template(&[" a ", "BÉ", "déjà"], &pieces), "bé-a-4-");
+        assert_eq!(fill_template(&[], &pieces), "--0-");
+    }
+
+    #[test]
+    fn test_extract_fixed() {
+        let extract_fixed = |line: &str, ranges| {
+            extract_fixed(line.as_bytes(), ranges)
+                .into_iter()
+                .map(|field| std::str::from_utf8(field).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let ranges = [0..4, 4..7, 7..usize::MAX];
+        assert_eq!(extract_fixed("ab  12 xyz  ", &ranges), ["ab", "12", "xyz"]);
+        assert_eq!(extract_fixed("ábcd", &ranges), ["ábcd", "", ""]);
+        assert_eq!(extract_fixed("", &ranges), ["", "", ""]);
+    }
+
+    #[test]
+    fn test_find_names() {
+        let header = ["title", "year", "director", "year-end"];
+
+        // Exact names and ranges of them
+        assert_eq!(find_names(&header, "year", false).unwrap(), vec![1..2]);
+        assert_eq!(
+            find_names(&header, "director,title", false).unwrap(),
+            vec![2..3, 0..1]
+        );
+        assert_eq!(
+            find_names(&header, "title-director", false).unwrap(),
+            vec![0..3]
+        );
+        assert_eq!(
+            find_names(&header, "year-", false).unwrap(),
+            vec![1..usize::MAX]
+        );
+        assert_eq!(find_names(&header, "-year", false).unwrap(), vec![0..2]);
+        assert_eq!(find_names(&header, "year-end", false).unwrap(), vec![3..4]);
+
+        // Patterns select every match
+        assert_eq!(find_names(&header, "^y", true).unwrap(), vec![1..2, 3..4]);
+        assert_eq!(
+            find_names(&header, "^d,^t", true).unwrap(),
+            vec![2..3, 0..1]
+        );
+
+        // Bad names
+        let res = find_names(&header, "rating", false);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"unknown field name: "rating""#
+        );
+        let res = find_names(&header, "title-rating", false);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"unknown field name: "rating""#
+        );
+        let res = find_names(&header, "director-title", false);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"field "director" must come before "title""#
+        );
+        assert!(find_names(&header, "-", false).is_err());
+        assert!(find_names(&header, "", false).is_err());
+        let res = find_names(&header, "(", true);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"invalid field pattern: "(""#
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let split = |splitter: Splitter, line: &str| {
+            splitter
+                .split(line.as_bytes())
+                .into_iter()
+                .map(|field| String::from_utf8(field).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            split(Splitter::Csv(b','), "a,\"b,c\""),
+            ["a", "\"b", "c\""]
+        );
+        assert_eq!(
+            split(Splitter::Str("::".to_string()), "a::b:c"),
+            ["a", "b:c"]
+        );
+        assert_eq!(split(Splitter::Str("::".to_string()), "abc"), ["abc"]);
+        let re = Regex::new(r"\s*,\s*").unwrap();
+        assert_eq!(split(Splitter::Regex(re), "a , b,c"), ["a", "b", "c"]);
+        assert_eq!(
+            split(Splitter::Whitespace, "  a \t b  c "),
+            ["a", "b", "c"]
+        );
+        assert!(split(Splitter::Whitespace, "   ").is_empty());
+    }
+
+    #[test]
+    fn test_sort_pos() {
+        assert!(sort_pos(vec![]).is_empty());
+        assert_eq!(sort_pos(vec![0..1, 0..1]), vec![0..1]);
+        assert_eq!(sort_pos(vec![6..7, 0..1, 2..5]), vec![0..1, 2..5, 6..7]);
+        assert_eq!(sort_pos(vec![2..5, 3..4, 4..8]), vec![2..8]);
+        assert_eq!(sort_pos(vec![1..2, 0..1]), vec![0..1, 1..2]);
+        assert_eq!(
+            sort_pos(vec![4..usize::MAX, 0..1, 6..9]),
+            vec![0..1, 4..usize::MAX]
+        );
+    }
+
+    #[test]
+    fn test_complement_pos() {
+        assert_eq!(complement_pos(vec![]), vec![0..usize::MAX]);
+        assert_eq!(complement_pos(vec![0..1]), vec![1..usize::MAX]);
+        assert_eq!(
+            complement_pos(vec![1..2, 4..6]),
+            vec![0..1, 2..4, 6..usize::MAX]
+        );
+        assert_eq!(complement_pos(vec![0..1, 1..2]), vec![2..usize::MAX]);
+        assert!(complement_pos(vec![0..usize::MAX]).is_empty());
+        assert_eq!(complement_pos(vec![2..usize::MAX]), vec![0..2]);
     }
 
     #[test]
     fn test_extract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
+        let rec = ["Captain", "Sham", "12345"];
         assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
         assert_eq!(
@@ -360,28 +1588,124 @@ mod unit_tests {
         );
         assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
         assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        assert_eq!(
+            extract_fields(&rec, &[1..usize::MAX]),
+            &["Sham", "12345"]
+        );
+        assert_eq!(extract_fields(&rec, &[5..usize::MAX]), &[] as &[&str]);
     }
 
     #[test]
     fn test_extract_chars() {
-        assert_eq!(extract_chars("", &[0..1]), "".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1]), "á".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác".to_string());
-        assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
-        assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
+        let extract_chars = on_str(extract_chars);
+        assert_eq!(extract_chars("", &[0..1], ""), "".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1], ""), "á".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1, 2..3], ""), "ác".to_string());
+        assert_eq!(extract_chars("ábc", &[0..3], ""), "ábc".to_string());
+        assert_eq!(extract_chars("ábc", &[2..3, 1..2], ""), "cb".to_string());
         assert_eq!(
-            extract_chars("ábc", &[0..1, 1..2, 4..5]),
+            extract_chars("ábc", &[0..1, 1..2, 4..5], ""),
             "áb".to_string()
         );
+        assert_eq!(
+            extract_chars("ábc", &[1..usize::MAX], ""),
+            "bc".to_string()
+        );
+        assert_eq!(extract_chars("ábc", &[0..1, 2..3], "::"), "á::c");
+        assert_eq!(extract_chars("ábc", &[0..1, 4..5], ":"), "á");
     }
 
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+        let line = "ábc".as_bytes();
+        assert_eq!(extract_bytes(line, &[0..1], "", false), b"\xc3");
+        assert_eq!(extract_bytes(line, &[0..2], "", false), "á".as_bytes());
+        assert_eq!(extract_bytes(line, &[0..3], "", false), "áb".as_bytes());
+        assert_eq!(extract_bytes(line, &[0..4], "", false), line);
+        assert_eq!(extract_bytes(line, &[3..4, 2..3], "", false), b"cb");
+        assert_eq!(
+            extract_bytes(line, &[0..2, 5..6], "", false),
+            "á".as_bytes()
+        );
+        assert_eq!(extract_bytes(line, &[2..usize::MAX], "", false), b"bc");
+        assert_eq!(
+            extract_bytes(line, &[0..2, 3..4], "::", false),
+            "á::c".as_bytes()
+        );
+        assert_eq!(
+            extract_bytes(line, &[0..2, 5..6], ":", false),
+            "á".as_bytes()
+        );
+        // Invalid UTF-8 is kept as is
+        assert_eq!(extract_bytes(b"caf\xe9!", &[3..5], "", false), b"\xe9!");
+    }
+
+    #[test]
+    fn test_extract_bytes_whole_chars() {
+        let line = "ábc".as_bytes();
+        assert_eq!(extract_bytes(line, &[0..1], "", true), b"");
+        assert_eq!(extract_bytes(line, &[0..3], "", true), "áb".as_bytes());
+        assert_eq!(extract_bytes(line, &[1..3], "", true), b"b");
+        let line = "a€b".as_bytes();
+        assert_eq!(extract_bytes(line, &[2..3], "", true), b"");
+        assert_eq!(extract_bytes(line, &[0..2, 3..5], ":", true), b"a:b");
+        assert_eq!(extract_bytes(b"caf\xe9", &[3..4], "", true), b"\xe9");
+    }
+
+    #[test]
+    fn test_extract_graphemes() {
+        let extract_graphemes = on_str(extract_graphemes);
+        // e and a combining acute accent, then a family emoji
+        let line = "e\u{301}x\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!";
+        assert_eq!(extract_graphemes(line, &[0..1], ""), "e\u{301}");
+        assert_eq!(
+            extract_graphemes(line, &[2..3], ""),
+            "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"
+        );
+        assert_eq!(extract_graphemes(line, &[0..1, 3..9], ":"), "e\u{301}:!");
+        assert_eq!(extract_graphemes("", &[0..1], ""), "");
+    }
+
+    #[test]
+    fn test_extract_columns() {
+        let extract_columns = on_str(extract_columns);
+        assert_eq!(extract_columns("abc", &[1..usize::MAX], ""), "bc");
+        // Wide chars take two columns and are left out unless both are
+        // selected
+        assert_eq!(extract_columns("a中文b", &[0..3], ""), "a中");
+        assert_eq!(extract_columns("a中文b", &[0..2], ""), "a");
+        assert_eq!(extract_columns("a中文b", &[2..6], ""), "文b");
+        assert_eq!(extract_columns("a中文b", &[0..1, 3..5], ":"), "a:文");
+        // A combining mark stays with its base
+        assert_eq!(extract_columns("e\u{301}z", &[0..1], ""), "e\u{301}");
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // Latin-1 é, and the first two bytes of €
+        let line = b"caf\xe9 \xe2\x82";
+        assert_eq!(split_chars(line).len(), 7);
+        assert_eq!(extract_chars(line, &[3..5], ""), b"\xe9 ");
+        assert_eq!(extract_graphemes(line, &[3..4, 5..6], ":"), b"\xe9:\xe2");
+        assert_eq!(extract_columns(line, &[4..usize::MAX], ""), b" \xe2\x82");
+        assert_eq!(
+            extract_fixed(line, &[0..4, 4..7]),
+            [&b"caf\xe9"[..], b"\xe2\x82"]
+        );
+        assert_eq!(trim(b" \xff\t"), b"\xff");
+        assert_eq!(trim(b" \t "), b"");
+        assert_eq!(
+            Splitter::Whitespace.split(b" \xff \xe9\tx"),
+            [b"\xff".to_vec(), b"\xe9".to_vec(), b"x".to_vec()]
+        );
+        assert_eq!(Transform::Upper.apply(b"caf\xe9"), b"CAF\xe9");
+        assert_eq!(Transform::Len.apply(b"caf\xe9"), b"4");
+        assert_eq!(
+            fill_template(
+                &[b"\xe9"],
+                &parse_template("{1}-{1:lower}").unwrap()
+            ),
+            b"\xe9-\xe9"
+        );
     }
 }
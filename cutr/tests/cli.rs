@@ -3,7 +3,7 @@ use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs;
+use std::{fs, path::Path};
 
 const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
@@ -58,7 +58,9 @@ fn dies_not_enough_args() -> Result<()> {
     dies(
         &[CSV],
         "the following required arguments were not provided:\n  \
-        <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>",
+        <--fields <FIELDS>|--field-names <NAMES>|--bytes <BYTES>|\
+        --chars <CHARS>|--columns <COLUMNS>|--widths <WIDTHS>|\
+        --spec <NAME:START-END,...>|--template <TEMPLATE>>",
     )
 }
 
@@ -97,19 +99,25 @@ fn dies_bad_digit_chars() -> Result<()> {
 fn dies_empty_delimiter() -> Result<()> {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        r#"--delim "" must be a single byte"#,
+        r#"--delim "" must not be empty"#,
     )
 }
 
 // --------------------------------------------------
 #[test]
-fn dies_bad_delimiter() -> Result<()> {
+fn dies_bad_regex_delimiter() -> Result<()> {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
-        r#"--delim ",," must be a single byte"#,
+        &[CSV, "-f", "1", "--regex-delimiter", "["],
+        r#"invalid --regex-delimiter "[""#,
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_whitespace_delimiter() -> Result<()> {
+    dies(&[CSV, "-f", "1", "-w", "-d", ","], "cannot be used with")
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_chars_bytes_fields() -> Result<()> {
@@ -162,14 +170,11 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
 }
 
 // --------------------------------------------------
-fn run_lossy(args: &[&str], expected_file: &str) -> Result<()> {
-    let contents = fs::read(expected_file)?;
-    let expected = String::from_utf8_lossy(&contents);
+fn run_bytes(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
     let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
     assert!(output.status.success());
-
-    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
-    assert_eq!(stdout, expected);
+    assert_eq!(output.stdout, expected);
     Ok(())
 }
 
@@ -209,6 +214,24 @@ fn tsv_f1_3() -> Result<()> {
     run(&[TSV, "-f", "1-3"], "tests/expected/movies1.tsv.f1-3.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn tsv_f2_open() -> Result<()> {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f_open_2() -> Result<()> {
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f3_1() -> Result<()> {
+    run(&[TSV, "-f", "3,1"], "tests/expected/movies1.tsv.f3,1.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn csv_f1() -> Result<()> {
@@ -263,6 +286,33 @@ fn csv_f1_3() -> Result<()> {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn csv_f2_open() -> Result<()> {
+    run(
+        &[CSV, "-f", "2-", "-d", ","],
+        "tests/expected/movies1.csv.f2-.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f_open_2() -> Result<()> {
+    run(
+        &[CSV, "-f", "-2", "-d", ","],
+        "tests/expected/movies1.csv.f-2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f3_1() -> Result<()> {
+    run(
+        &[CSV, "-f", "3,1", "-d", ","],
+        "tests/expected/movies1.csv.f3,1.dcomma.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn tsv_b1() -> Result<()> {
@@ -278,7 +328,7 @@ fn tsv_b2() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn tsv_b8() -> Result<()> {
-    run_lossy(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
+    run_bytes(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
 }
 
 // --------------------------------------------------
@@ -296,7 +346,49 @@ fn tsv_b2_3() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn tsv_b1_8() -> Result<()> {
-    run_lossy(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
+    run_bytes(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b8_open() -> Result<()> {
+    run_bytes(&[TSV, "-b", "8-"], "tests/expected/movies1.tsv.b8-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b_open_2() -> Result<()> {
+    run(&[TSV, "-b", "-2"], "tests/expected/movies1.tsv.b-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b8_1_2() -> Result<()> {
+    run_bytes(
+        &[TSV, "-b", "8,1-2"],
+        "tests/expected/movies1.tsv.b8,1-2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_b8_open() -> Result<()> {
+    run_bytes(&[CSV, "-b", "8-"], "tests/expected/movies1.csv.b8-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_b_open_2() -> Result<()> {
+    run(&[CSV, "-b", "-2"], "tests/expected/movies1.csv.b-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_b8_1_2() -> Result<()> {
+    run_bytes(
+        &[CSV, "-b", "8,1-2"],
+        "tests/expected/movies1.csv.b8,1-2.out",
+    )
 }
 
 // --------------------------------------------------
@@ -338,5 +430,655 @@ fn tsv_c1_8() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn repeated_value() -> Result<()> {
+    run(
+        &[BOOKS, "-c", "1,1", "--preserve-order"],
+        "tests/expected/books.c1,1.preserve.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn repeated_value_once() -> Result<()> {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn preserve_order() -> Result<()> {
+    run(
+        &[BOOKS, "-f", "3,1", "--preserve-order"],
+        "tests/expected/books.f3,1.preserve.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn complement_fields() -> Result<()> {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn complement_bytes() -> Result<()> {
+    run(
+        &[TSV, "-b", "2-3", "--complement"],
+        "tests/expected/movies1.tsv.b2-3.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn complement_all_fields() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1-2", "--complement"])
+        .write_stdin("a\tb\nc\td\te\n")
+        .assert()
+        .success()
+        .stdout("\ne\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_fields() -> Result<()> {
+    run(
+        &[TSV, "-f", "1,3", "--output-delimiter", " | "],
+        "tests/expected/movies1.tsv.f1,3.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter_bytes() -> Result<()> {
+    run(
+        &[TSV, "-b", "1-2,4", "--output-delimiter", "::"],
+        "tests/expected/movies1.tsv.b1-2,4.odelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> Result<()> {
+    let input = "a\tb\nno delimiter\nc\td\n";
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("b\nno delimiter\nd\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2", "-s"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("b\nd\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_delimited_bytes() -> Result<()> {
+    dies(
+        &[CSV, "-b", "1", "-s"],
+        "suppressing non-delimited lines makes sense \
+        only when operating on fields",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_complement_preserve_order() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "--complement", "--preserve-order"],
+        "cannot be used with",
+    )
+}
+
+// --------------------------------------------------
+fn run_raw(file: &str, delimiter: &str) -> Result<()> {
+    let name = Path::new(file).file_name().unwrap().to_string_lossy();
+    for fields in ["1", "2", "3", "1,3", "2-"] {
+        run(
+            &[file, "--raw", "-d", delimiter, "-f", fields],
+            &format!("tests/expected/{name}.f{fields}.raw.out"),
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_books_tsv() -> Result<()> {
+    run_raw(BOOKS, "\t")
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_books_csv() -> Result<()> {
+    run_raw("tests/inputs/books.csv", ",")
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_books_txt() -> Result<()> {
+    run_raw("tests/inputs/books.txt", " ")
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_keeps_quotes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--raw", "-d", ",", "-f", "2,3"])
+        .write_stdin("a,\"b,c\",d\n")
+        .assert()
+        .success()
+        .stdout("\"b,c\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["--raw", "-d", ",", "-f", "1,2", "--output-delimiter", ";"])
+        .write_stdin("a;b,c\n")
+        .assert()
+        .success()
+        .stdout("a;b;c\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_names(args: &[&str], expected: &str) -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/movies2.csv", "-d", ","])
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names() -> Result<()> {
+    run_names(
+        &["-F", "year,title"],
+        "The Blues Brothers,1980\n\
+        Les Misérables,2012\n\
+        \"To Sir, with Love\",1967\n",
+    )?;
+    run_names(
+        &["-F", "year,title", "--preserve-order", "--header"],
+        "year,title\n\
+        1980,The Blues Brothers\n\
+        2012,Les Misérables\n\
+        1967,\"To Sir, with Love\"\n",
+    )?;
+    run_names(
+        &["--field-names", "year-", "--header"],
+        "year,director\n\
+        1980,John Landis\n\
+        2012,Tom Hooper\n\
+        1967,James Clavell\n",
+    )?;
+    run_names(
+        &["-F", "year", "--complement", "--output-delimiter", " | "],
+        "The Blues Brothers | John Landis\n\
+        Les Misérables | Tom Hooper\n\
+        To Sir, with Love | James Clavell\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names_regex() -> Result<()> {
+    run_names(
+        &["-F", "^t,^d", "--name-regex", "--header"],
+        "title,director\n\
+        The Blues Brothers,John Landis\n\
+        Les Misérables,Tom Hooper\n\
+        \"To Sir, with Love\",James Clavell\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names_raw() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "--raw", "-F", "Title,Author", "--header"])
+        .assert()
+        .success()
+        .stdout(
+            "Author\tTitle\n\
+            Émile Zola\tLa Confession de Claude\n\
+            Samuel Beckett\tWaiting for Godot\n\
+            Jules Verne\t20,000 Leagues Under the Sea\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> Result<()> {
+    dies(
+        &["tests/inputs/movies2.csv", "-d", ",", "-F", "title,rating"],
+        r#"unknown field name: "rating""#,
+    )?;
+    dies(
+        &[
+            "tests/inputs/movies2.csv",
+            "-d",
+            ",",
+            "-F",
+            "x",
+            "--name-regex",
+        ],
+        r#"no field name matches "x""#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_header_without_names() -> Result<()> {
+    dies(&[CSV, "-f", "1", "--header"], "cannot be used with")?;
+    dies(&[CSV, "-b", "1", "--name-regex"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_char_delimiter() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-d", "::", "-f", "1,3"])
+        .write_stdin("a::b:c::\"d\"\nnone:here\n")
+        .assert()
+        .success()
+        .stdout("a::\"d\"\nnone:here\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--regex-delimiter", r"\s*[,;]\s*", "-f", "2-"])
+        .write_stdin("a , b;c\nd;e\n")
+        .assert()
+        .success()
+        .stdout("b c\ne\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace() -> Result<()> {
+    let ps = "    PID TTY          TIME CMD
+   1234 pts/0    00:00:01 bash
+  56789 pts/0    00:00:00 ps
+";
+    Command::cargo_bin(PRG)?
+        .args(["-w", "-f", "1,4"])
+        .write_stdin(ps)
+        .assert()
+        .success()
+        .stdout("PID CMD\n1234 bash\n56789 ps\n");
+    Command::cargo_bin(PRG)?
+        .args(["--whitespace", "-F", "CMD,PID", "--preserve-order"])
+        .args(["--output-delimiter", "\t"])
+        .write_stdin(ps)
+        .assert()
+        .success()
+        .stdout("bash\t1234\nps\t56789\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes() -> Result<()> {
+    let input = "e\u{301}te\u{301}\n\u{1F1EB}\u{1F1F7}\u{1F1EE}\u{1F1F9}\n";
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1", "--graphemes"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("e\u{301}\n\u{1F1EB}\u{1F1F7}\n");
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("e\n\u{1F1EB}\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn columns() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--columns", "-4,7-"])
+        .args(["--output-delimiter", "|"])
+        .write_stdin("名前 age\nabcdefgh\n")
+        .assert()
+        .success()
+        .stdout("名前|ge\nabcd|gh\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_whole_chars() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-b", "1-2", "-n"])
+        .assert()
+        .success()
+        .stdout("Au\nÉ\nSa\nJu\n");
+    Command::cargo_bin(PRG)?
+        .args([BOOKS, "-b", "1", "-n"])
+        .assert()
+        .success()
+        .stdout("A\n\nS\nJ\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn widths() -> Result<()> {
+    run(&["tests/inputs/books.txt", "--widths", "20,5,30"], BOOKS)
+}
+
+// --------------------------------------------------
+#[test]
+fn spec() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/books.txt", "-d", ",", "--header"])
+        .args(["--spec", "title:26-,year:21-24"])
+        .assert()
+        .success()
+        .stdout(
+            "title,year\n\
+            Title,Year\n\
+            La Confession de Claude,1865\n\
+            Waiting for Godot,1952\n\
+            \"20,000 Leagues Under the Sea\",1870\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_spec() -> Result<()> {
+    dies(&[CSV, "--spec", "title"], r#"invalid field spec: "title""#)?;
+    dies(&[CSV, "--widths", "10,0"], r#"illegal list value: "0""#)
+}
+
+// --------------------------------------------------
+fn run_stdout(args: &[&str], expected: &str) -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn input_format() -> Result<()> {
+    run_stdout(
+        &[
+            "tests/inputs/movies2.csv",
+            "-f",
+            "1",
+            "--input-format",
+            "raw",
+        ],
+        "title,year,director\n\
+        The Blues Brothers,1980,John Landis\n\
+        Les Misérables,2012,Tom Hooper\n\
+        \"To Sir, with Love\",1967,James Clavell\n",
+    )?;
+    run_stdout(
+        &[BOOKS, "--input-format", "csv", "-f", "2", "-d", "\t"],
+        "Year\n1865\n1952\n1870\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_format_jsonl() -> Result<()> {
+    run_stdout(
+        &[CSV, "-d", ",", "-f", "1,3", "--output-format", "jsonl"],
+        "{\"title\":\"The Blues Brothers\",\"director\":\"John Landis\"}\n\
+        {\"title\":\"Les Misérables\",\"director\":\"Tom Hooper\"}\n",
+    )?;
+    run_stdout(
+        &[BOOKS, "-F", "Year,Author", "--output-format", "jsonl"],
+        "{\"Author\":\"Émile Zola\",\"Year\":\"1865\"}\n\
+        {\"Author\":\"Samuel Beckett\",\"Year\":\"1952\"}\n\
+        {\"Author\":\"Jules Verne\",\"Year\":\"1870\"}\n",
+    )?;
+    run_stdout(
+        &[
+            "tests/inputs/books.txt",
+            "--spec",
+            "year:21-24,author:1-20",
+            "--output-format",
+            "jsonl",
+        ],
+        "{\"year\":\"Year\",\"author\":\"Author\"}\n\
+        {\"year\":\"1865\",\"author\":\"Émile Zola\"}\n\
+        {\"year\":\"1952\",\"author\":\"Samuel Beckett\"}\n\
+        {\"year\":\"1870\",\"author\":\"Jules Verne\"}\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn output_format_markdown() -> Result<()> {
+    run_stdout(
+        &[
+            "tests/inputs/books.txt",
+            "--widths",
+            "20,5",
+            "--output-format",
+            "markdown",
+        ],
+        "| Author | Year |\n\
+        | --- | --- |\n\
+        | Émile Zola | 1865 |\n\
+        | Samuel Beckett | 1952 |\n\
+        | Jules Verne | 1870 |\n",
+    )?;
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2,1", "--output-format", "markdown"])
+        .write_stdin("a\tb|c\n1\t2\n")
+        .assert()
+        .success()
+        .stdout("| a | b\\|c |\n| --- | --- |\n| 1 | 2 |\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_format_tsv() -> Result<()> {
+    run_stdout(
+        &["tests/inputs/movies2.csv", "-d", ",", "-F", "title-year"],
+        "The Blues Brothers,1980\n\
+        Les Misérables,2012\n\
+        \"To Sir, with Love\",1967\n",
+    )?;
+    run_stdout(
+        &[
+            "tests/inputs/movies2.csv",
+            "--input-format",
+            "csv",
+            "-F",
+            "title-year",
+            "--output-format",
+            "tsv",
+        ],
+        "The Blues Brothers\t1980\n\
+        Les Misérables\t2012\n\
+        To Sir, with Love\t1967\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_format() -> Result<()> {
+    dies(
+        &[CSV, "-f", "1", "--input-format", "csv", "-d", ";;"],
+        r#"--delim ";;" must be a single byte for csv"#,
+    )?;
+    dies(
+        &[CSV, "-f", "1", "--output-format", "xml"],
+        "invalid value 'xml' for '--output-format <FORMAT>'",
+    )?;
+    dies(
+        &[CSV, "-b", "1", "--output-format", "jsonl"],
+        "cannot be used with",
+    )
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input: &[u8], expected: &[u8]) -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_bytes() -> Result<()> {
+    // Latin-1 "café" and a lone continuation byte
+    let input = b"caf\xe9\tx\n\x80abc\nok\n";
+    run_stdin(&["-b", "4-"], input, b"\xe9\tx\nc\n\n")?;
+    run_stdin(
+        &["-b", "1,4", "--output-delimiter", ":"],
+        input,
+        b"c:\xe9\n\x80:c\no\n",
+    )?;
+    run_stdin(
+        &["-b", "1-4", "-n"],
+        b"\xe2\x82\xac\xe2\x82\n",
+        b"\xe2\x82\xac\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_chars_fields() -> Result<()> {
+    // Bytes that aren't valid UTF-8 pass through and count as a char
+    let input = b"caf\xe9\tx\nok\ty\n";
+    run_stdin(&["-c", "4-"], input, b"\xe9\tx\ny\n")?;
+    run_stdin(&["-f", "1"], input, b"caf\xe9\nok\n")?;
+    run_stdin(&["-f", "2", "-d", ";;"], b"\xff;;a\n", b"a\n")?;
+    run_stdin(&["-f", "2", "-w"], b" \xff  b\xe9\n", b"b\xe9\n")?;
+    run_stdin(&["-F", "b,a"], b"a\tb\n\xe9\t2\n", b"\xe9\t2\n")?;
+    run_stdin(&["--widths", "2,2"], input, b"ca\tf\xe9\nok\ty\n")?;
+    run_stdin(
+        &["--template", "{2}:{1:upper}:{1:len}"],
+        input,
+        b"x:CAF\xe9:4\ny:OK:2\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    let input = b"a\tb\nc\0d\te\0no delimiter";
+    run_stdin(&["-z", "-f", "2"], input, b"b\nc\0e\0no delimiter\0")?;
+    run_stdin(&["-z", "-f", "2", "-s"], input, b"b\nc\0e\0")?;
+    run_stdin(&["-z", "-b", "1,3"], input, b"ab\0de\0n \0")?;
+    run_stdin(&["-z", "-c", "2-"], input, b"\tb\nc\0\te\0o delimiter\0")?;
+    run_stdin(
+        &[
+            "--zero-terminated",
+            "-d",
+            ",",
+            "-f",
+            "1,3",
+            "--output-format",
+            "jsonl",
+        ],
+        b"k,v,w\0\"a\0b\",1,2\0",
+        b"{\"k\":\"a\\u0000b\",\"w\":\"2\"}\0",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn template() -> Result<()> {
+    run_stdout(
+        &[
+            "tests/inputs/movies2.csv",
+            "-d",
+            ",",
+            "--template",
+            r"{3}\t{1}\tconst\t{2:upper}",
+        ],
+        "director\ttitle\tconst\tYEAR\n\
+        John Landis\tThe Blues Brothers\tconst\t1980\n\
+        Tom Hooper\tLes Misérables\tconst\t2012\n\
+        James Clavell\tTo Sir, with Love\tconst\t1967\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "--template", "{1}{1} {{{2:trim}}} {3:len}"],
+        b"a, b ,cde\nno delimiter\n",
+        b"aa {b} 3\nno delimiterno delimiter {} 0\n",
+    )?;
+    run_stdin(
+        &["-d", ",", "-s", "--template", "{2:lower}"],
+        b"a,B\nno delimiter\n",
+        b"b\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_template() -> Result<()> {
+    dies(
+        &[CSV, "--template", "{1:title}"],
+        r#"unknown template transform: "title""#,
+    )?;
+    dies(
+        &[CSV, "--template", "{0}"],
+        r#"invalid template field: "{0}""#,
+    )?;
+    dies(&[CSV, "--template", "{1", "-f", "1"], "cannot be used with")?;
+    dies(
+        &[CSV, "--template", "{1}", "--output-format", "jsonl"],
+        "cannot be used with",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_fields() -> Result<()> {
+    // Like cut, nothing selected or one empty field is an empty line
+    let input = b"a\tb\na\t\tc\n";
+    run_stdin(&["-f", "3-"], input, b"\nc\n")?;
+    run_stdin(&["-f", "2"], input, b"b\n\n")?;
+    run_stdin(&["-f", "2-3"], input, b"b\n\tc\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn output_format_jsonl_undelimited() -> Result<()> {
+    let input = "a\tb\nno delimiter\nc\td\n";
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2", "--output-format", "jsonl"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("{\"b\":\"no delimiter\"}\n{\"b\":\"d\"}\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", "2", "-s", "--output-format", "jsonl"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("{\"b\":\"d\"}\n");
+    Ok(())
+}
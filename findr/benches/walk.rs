@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+const PRG: &str = env!("CARGO_BIN_EXE_findr");
+
+// --------------------------------------------------
+// 100 top-level directories, each with 10 subdirectories of 99 files:
+// 100 + 1,000 + 99,000 = 100,100 entries below the root.
+fn synthetic_tree() -> TempDir {
+    let dir = tempfile::tempdir().expect("tempdir");
+    for i in 0..100 {
+        for j in 0..10 {
+            let sub = dir.path().join(format!("d{i:03}/s{j}"));
+            fs::create_dir_all(&sub).expect("create_dir_all");
+            for k in 0..99 {
+                fs::write(sub.join(format!("f{k:02}.txt")), "").expect("write");
+            }
+        }
+    }
+    dir
+}
+
+// --------------------------------------------------
+fn findr(root: &Path, args: &[&str]) {
+    let status = Command::new(PRG)
+        .arg(root)
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .expect("findr");
+    assert!(status.success());
+}
+
+// --------------------------------------------------
+fn walk(c: &mut Criterion) {
+    let tree = synthetic_tree();
+    let mut group = c.benchmark_group("walk_100k");
+    group.sample_size(10);
+
+    for jobs in ["1", "2", "4", "8"] {
+        for sort in ["none", "name"] {
+            group.bench_function(format!("j{jobs}_sort_{sort}"), |b| {
+                b.iter(|| findr(tree.path(), &["-j", jobs, "--sort", sort]))
+            });
+        }
+    }
+
+    group.bench_function("j8_type_f_name", |b| {
+        b.iter(|| findr(tree.path(), &["-j", "8", "-t", "f", "-n", "f0*.txt"]))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, walk);
+criterion_main!(benches);
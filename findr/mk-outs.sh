@@ -39,3 +39,12 @@ find "$IN_DIR/a" "$IN_DIR/d" -name \*.txt > "$OUT_DIR/name_txt_path_a_d.txt"
 find "$IN_DIR" -name a* > "$OUT_DIR/name_a.txt"
 find "$IN_DIR" -type f -name a* > "$OUT_DIR/type_f_name_a.txt"
 find "$IN_DIR" -type d -name a* > "$OUT_DIR/type_d_name_a.txt"
+
+find "$IN_DIR" -path \*/b/\* > "$OUT_DIR/path_glob_b.txt"
+find "$IN_DIR" -regex csv > "$OUT_DIR/regex_partial.txt"
+
+find "$IN_DIR" -xtype f > "$OUT_DIR/xtype_f.txt"
+find "$IN_DIR" -xtype l > "$OUT_DIR/xtype_l.txt"
+find -L "$IN_DIR" -type f > "$OUT_DIR/follow_type_f.txt"
+find -L "$IN_DIR" -type l > "$OUT_DIR/follow_type_l.txt"
+find "$IN_DIR" -samefile "$IN_DIR/a/b/b.csv" > "$OUT_DIR/samefile_b.txt"
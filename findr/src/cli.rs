@@ -0,0 +1,863 @@
+use crate::duplicates;
+use crate::output::{Format, PathStyle, Printer, Summary};
+use crate::watch::Watcher;
+#[cfg(unix)]
+use crate::Perm;
+use crate::{build_glob, build_regex, Entry, EntryType, FindQuery};
+#[cfg(unix)]
+use anyhow::anyhow;
+use anyhow::{bail, Result};
+use clap::{
+    builder::PossibleValue, ArgAction, CommandFactory, Parser, ValueEnum,
+};
+#[cfg(unix)]
+use nix::unistd::{Group, User};
+use regex::bytes;
+use std::ffi::OsString;
+use std::fs::FileType;
+use std::io::{self, BufWriter, IsTerminal};
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+/// Rust version of `find`
+struct Args {
+    /// Search path(s)
+    #[arg(value_name = "PATH", default_value = ".")]
+    paths: Vec<String>,
+
+    /// Follow all symbolic links
+    #[arg(short('L'), overrides_with_all(["follow_roots", "follow_none"]))]
+    follow_all: bool,
+
+    /// Follow symbolic links given as search paths only
+    #[arg(short('H'), overrides_with_all(["follow_all", "follow_none"]))]
+    follow_roots: bool,
+
+    /// Never follow symbolic links (default)
+    #[arg(short('P'), overrides_with_all(["follow_all", "follow_roots"]))]
+    follow_none: bool,
+
+    /// Number of threads reading directories, 0 for one per CPU
+    #[arg(short('j'), long("jobs"), value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Regular text files with a line matching REGEX
+    #[arg(
+        long("contains"),
+        value_name = "REGEX",
+        value_parser(parse_contents),
+        allow_hyphen_values(true),
+        action(ArgAction::Append)
+    )]
+    contents: Vec<String>,
+
+    /// Skip what .gitignore, .ignore and global git excludes ignore, and .git
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Skip entries whose names start with a dot
+    #[arg(long("no-hidden"))]
+    no_hidden: bool,
+
+    /// Output format
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Print groups of regular files with the same contents
+    #[arg(
+        long,
+        conflicts_with_all = ["format", "sort", "reverse", "limit"]
+    )]
+    duplicates: bool,
+
+    /// After the walk, keep reporting matching entries as they are
+    /// created, written to or deleted
+    #[arg(long, conflicts_with = "duplicates")]
+    watch: bool,
+
+    /// List the contents of each directory before the directory itself
+    #[arg(short('d'), long("depth-first"), visible_alias("depth"))]
+    depth_first: bool,
+
+    /// Print paths relative to their search path
+    #[arg(long, conflicts_with = "absolute")]
+    relative: bool,
+
+    /// Print absolute paths
+    #[arg(long)]
+    absolute: bool,
+
+    /// Output order
+    #[arg(long, value_name = "ORDER", value_enum, default_value_t = Sort::None)]
+    sort: Sort,
+
+    /// Reverse the output order
+    #[arg(long)]
+    reverse: bool,
+
+    /// Stop after N matches
+    #[arg(long, value_name = "N", conflicts_with = "watch")]
+    limit: Option<usize>,
+
+    /// Print counts and total sizes by type and extension instead of the
+    /// matches, leaving out the sizes of directories themselves
+    #[arg(long, conflicts_with_all = ["format", "duplicates", "watch"])]
+    summary: bool,
+
+    /// Base name glob(s)
+    #[arg(
+        short('n'),
+        long("name"),
+        value_name = "GLOB",
+        value_parser(parse_glob),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    names: Vec<String>,
+
+    /// Case-insensitive base name glob(s)
+    #[arg(
+        long("iname"),
+        value_name = "GLOB",
+        value_parser(parse_glob),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    inames: Vec<String>,
+
+    /// Full path glob(s)
+    #[arg(
+        long("path"),
+        value_name = "GLOB",
+        value_parser(parse_glob),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    path_globs: Vec<String>,
+
+    /// Case-insensitive full path glob(s)
+    #[arg(
+        long("ipath"),
+        value_name = "GLOB",
+        value_parser(parse_glob),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    ipath_globs: Vec<String>,
+
+    /// Full path regular expression(s)
+    #[arg(
+        long("regex"),
+        value_name = "REGEX",
+        value_parser(parse_regex),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    regexes: Vec<String>,
+
+    /// Case-insensitive full path regular expression(s)
+    #[arg(
+        long("iregex"),
+        value_name = "REGEX",
+        value_parser(parse_regex),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    iregexes: Vec<String>,
+
+    /// Entry types
+    #[arg(
+        short('t'),
+        long("type"),
+        value_name = "TYPE",
+        value_parser(clap::value_parser!(EntryType)),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    entry_types: Vec<EntryType>,
+
+    /// Entry types, checking the target of symbolic links
+    #[arg(
+        long("xtype"),
+        value_name = "TYPE",
+        value_parser(clap::value_parser!(EntryType)),
+        action(ArgAction::Append),
+        num_args(0..)
+    )]
+    link_target_types: Vec<EntryType>,
+
+    #[cfg(unix)]
+    /// Hard links to the same file
+    #[arg(long("samefile"), value_name = "FILE", action(ArgAction::Append))]
+    same_files: Vec<PathBuf>,
+
+    #[cfg(unix)]
+    /// Inode number
+    #[arg(long("inum"), value_name = "INODE", action(ArgAction::Append))]
+    inodes: Vec<u64>,
+
+    /// Don't descend into directories on other file systems
+    #[arg(long, visible_alias("mount"))]
+    xdev: bool,
+
+    #[cfg(target_os = "linux")]
+    /// File system type, as /proc/self/mountinfo names it
+    #[arg(long("fstype"), value_name = "TYPE", action(ArgAction::Append))]
+    fs_types: Vec<String>,
+
+    #[cfg(unix)]
+    /// Permission bits: exactly MODE, all of -MODE or any of /MODE
+    #[arg(
+        long("perm"),
+        value_name = "MODE",
+        value_parser(parse_perm),
+        allow_hyphen_values(true),
+        action(ArgAction::Append)
+    )]
+    perms: Vec<Perm>,
+
+    #[cfg(unix)]
+    /// Owner name or UID
+    #[arg(
+        long("user"),
+        value_name = "USER",
+        value_parser(parse_user),
+        action(ArgAction::Append)
+    )]
+    users: Vec<u32>,
+
+    #[cfg(unix)]
+    /// Group name or GID
+    #[arg(
+        long("group"),
+        value_name = "GROUP",
+        value_parser(parse_group),
+        action(ArgAction::Append)
+    )]
+    groups: Vec<u32>,
+
+    #[cfg(unix)]
+    /// Owner UID has no user
+    #[arg(long("nouser"))]
+    no_user: bool,
+
+    #[cfg(unix)]
+    /// Owner GID has no group
+    #[arg(long("nogroup"))]
+    no_group: bool,
+
+    #[cfg(unix)]
+    /// Readable by the current user
+    #[arg(long)]
+    readable: bool,
+
+    #[cfg(unix)]
+    /// Writable by the current user
+    #[arg(long)]
+    writable: bool,
+
+    #[cfg(unix)]
+    /// Executable (or searchable) by the current user
+    #[arg(long)]
+    executable: bool,
+
+    #[cfg(target_os = "linux")]
+    /// Extended attribute NAME, with VALUE if given
+    #[arg(
+        long("xattr"),
+        value_name = "NAME[=VALUE]",
+        value_parser(parse_xattr),
+        action(ArgAction::Append)
+    )]
+    xattrs: Vec<Xattr>,
+
+    #[cfg(target_os = "linux")]
+    /// Has a POSIX access or default ACL
+    #[arg(long("has-acl"))]
+    has_acl: bool,
+
+    #[cfg(target_os = "linux")]
+    /// Has Linux file capabilities
+    #[arg(long)]
+    capabilities: bool,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, ValueEnum)]
+enum Sort {
+    /// Directory contents in name order, the same for any number of threads
+    Name,
+    /// All matches by path
+    Path,
+    /// All matches by size, smallest first
+    Size,
+    /// All matches by modification time, oldest first
+    Mtime,
+    /// Whatever order the entries are found in, which is fastest
+    None,
+}
+
+impl Sort {
+    /// Whether the order needs every match before the first is printed
+    fn is_global(&self) -> bool {
+        matches!(self, Sort::Path | Sort::Size | Sort::Mtime)
+    }
+
+    fn sort(&self, entries: &mut [Entry]) {
+        // A missing size or time sorts as the smallest, and ties by path
+        match self {
+            Sort::Path => entries.sort_by(|a, b| a.path().cmp(b.path())),
+            Sort::Size => entries.sort_by_cached_key(|entry| {
+                let size = entry.metadata().map_or(0, |md| md.len());
+                (size, entry.path().to_path_buf())
+            }),
+            Sort::Mtime => entries.sort_by_cached_key(|entry| {
+                let mtime = entry
+                    .metadata()
+                    .and_then(|md| md.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (mtime, entry.path().to_path_buf())
+            }),
+            Sort::Name | Sort::None => {}
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct Xattr {
+    name: String,
+    // Any value when not given
+    value: Option<Vec<u8>>,
+}
+
+impl EntryType {
+    pub(crate) fn of(file_type: &FileType) -> Option<EntryType> {
+        EntryType::value_variants()
+            .iter()
+            .find(|entry_type| entry_type.is_match(file_type))
+            .cloned()
+    }
+
+    /// The letter `--type` takes for this type
+    pub(crate) fn letter(&self) -> &'static str {
+        match self {
+            EntryType::Dir => "d",
+            EntryType::File => "f",
+            EntryType::Link => "l",
+            EntryType::Block => "b",
+            EntryType::Char => "c",
+            EntryType::Fifo => "p",
+            EntryType::Socket => "s",
+        }
+    }
+}
+
+impl ValueEnum for EntryType {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            EntryType::Dir,
+            EntryType::File,
+            EntryType::Link,
+            EntryType::Block,
+            EntryType::Char,
+            EntryType::Fifo,
+            EntryType::Socket,
+        ]
+    }
+
+    fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(self.letter()))
+    }
+}
+
+// `find` spells its long options with a single dash (`-name`), which clap
+// would read as a bundle of short flags. These get rewritten to `--name`.
+const FIND_STYLE_ARGS: &[&str] = &[
+    "name",
+    "iname",
+    "path",
+    "ipath",
+    "regex",
+    "iregex",
+    "type",
+    "xtype",
+    "samefile",
+    "inum",
+    "xdev",
+    "depth",
+    "mount",
+    "fstype",
+    "perm",
+    "user",
+    "group",
+    "nouser",
+    "nogroup",
+    "readable",
+    "writable",
+    "executable",
+    "contains",
+    "xattr",
+    "has-acl",
+    "capabilities",
+];
+
+pub fn run() -> Result<()> {
+    let args = Args::parse_from(normalize_args(std::env::args_os()));
+    match find(&args) {
+        // The reader went away, e.g. `findr / | head`: just stop
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        res => res,
+    }
+}
+
+// --------------------------------------------------
+fn find(args: &Args) -> Result<()> {
+    let mut query = FindQuery::new()
+        .follow_links(args.follow_all)
+        .follow_root_links(args.follow_all || args.follow_roots)
+        .sort_by_name(args.sort == Sort::Name)
+        .threads(args.jobs)
+        .gitignore(args.gitignore)
+        .skip_hidden(args.no_hidden)
+        .same_file_system(args.xdev)
+        .contents_first(args.depth_first);
+    for entry_type in &args.entry_types {
+        query = query.entry_type(entry_type.clone());
+    }
+    for entry_type in &args.link_target_types {
+        query = query.link_target_type(entry_type.clone());
+    }
+    for glob in &args.names {
+        query = query.name(glob)?;
+    }
+    for glob in &args.inames {
+        query = query.iname(glob)?;
+    }
+    for glob in &args.path_globs {
+        query = query.path_glob(glob)?;
+    }
+    for glob in &args.ipath_globs {
+        query = query.ipath_glob(glob)?;
+    }
+    for pattern in &args.regexes {
+        query = query.regex(pattern)?;
+    }
+    for pattern in &args.iregexes {
+        query = query.iregex(pattern)?;
+    }
+    #[cfg(unix)]
+    let mut query = with_unix_tests(query, args)?;
+    for pattern in &args.contents {
+        query = query.contains(pattern)?;
+    }
+
+    // Flush every line for a terminal, otherwise only when the buffer fills
+    let flush_lines = io::stdout().is_terminal();
+    let out = BufWriter::new(io::stdout().lock());
+    let path_style = if args.relative {
+        PathStyle::Relative
+    } else if args.absolute {
+        PathStyle::Absolute
+    } else {
+        PathStyle::AsFound
+    };
+    let mut printer = Printer::new(args.format, path_style, out, flush_lines);
+    let mut candidates = vec![];
+    let mut limit = args.limit.unwrap_or(usize::MAX);
+    // Sorting, reversing and summing up all wait for the last match
+    let collect = args.sort.is_global() || args.reverse || args.summary;
+    let mut matches = vec![];
+    let mut watcher = if args.watch {
+        if !matches!(args.format, Format::Text | Format::Ndjson) {
+            bail!("--watch prints text or ndjson");
+        }
+        Some(Watcher::new(args.format, path_style, &query.opts)?)
+    } else {
+        None
+    };
+
+    for path in &args.paths {
+        if limit == 0 {
+            break;
+        }
+        let mut walked_dirs = vec![];
+        let entries = query
+            .walk(Path::new(path))
+            .inspect(|entry| match entry {
+                Ok(entry) if args.watch && entry.file_type().is_dir() => {
+                    walked_dirs
+                        .push((entry.path().to_path_buf(), entry.depth()));
+                }
+                _ => {}
+            })
+            .filter_map(|entry| {
+                match entry.and_then(|entry| {
+                    Ok(query.try_match(&entry)?.then_some(entry))
+                }) {
+                    Err(e) => {
+                        eprintln!("{e}");
+                        None
+                    }
+                    Ok(entry) => entry,
+                }
+            });
+
+        if args.duplicates {
+            candidates.extend(entries);
+            continue;
+        }
+        if collect {
+            matches.extend(entries);
+        } else {
+            for entry in entries.take(limit) {
+                limit -= 1;
+                printer.print(&entry)?;
+                if let Some(watcher) = &mut watcher {
+                    watcher.add_match(&entry);
+                }
+            }
+        }
+
+        if let Some(watcher) = &mut watcher {
+            watcher.add_walked(walked_dirs);
+        }
+    }
+
+    if args.duplicates {
+        let groups = duplicates::find_duplicates(candidates);
+        return duplicates::print_groups(BufWriter::new(io::stdout()), &groups);
+    }
+
+    if collect {
+        args.sort.sort(&mut matches);
+        if args.reverse {
+            matches.reverse();
+        }
+        matches.truncate(limit);
+
+        if args.summary {
+            let mut summary = Summary::default();
+            for entry in &matches {
+                summary.add(entry);
+            }
+            return summary.print(BufWriter::new(io::stdout()));
+        }
+        for entry in &matches {
+            printer.print(entry)?;
+            if let Some(watcher) = &mut watcher {
+                watcher.add_match(entry);
+            }
+        }
+    }
+
+    printer.finish()?;
+    match watcher {
+        Some(watcher) => watcher.run(io::stdout().lock(), |entry: &Entry| {
+            query.try_match(entry).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                false
+            })
+        }),
+        None => Ok(()),
+    }
+}
+
+// --------------------------------------------------
+// Add the tests on metadata that only Unix has
+#[cfg(unix)]
+fn with_unix_tests<'a>(
+    mut query: FindQuery<'a>,
+    args: &Args,
+) -> Result<FindQuery<'a>> {
+    query = query
+        .no_user(args.no_user)
+        .no_group(args.no_group)
+        .readable(args.readable)
+        .writable(args.writable)
+        .executable(args.executable);
+    for path in &args.same_files {
+        query = query.same_file(path)?;
+    }
+    for inode in &args.inodes {
+        query = query.inum(*inode);
+    }
+    for perm in &args.perms {
+        query = query.perm(*perm);
+    }
+    for uid in &args.users {
+        query = query.user(*uid);
+    }
+    for gid in &args.groups {
+        query = query.group(*gid);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        query = query.has_acl(args.has_acl).capabilities(args.capabilities);
+        for fs_type in &args.fs_types {
+            query = query.fs_type(fs_type)?;
+        }
+        for xattr in &args.xattrs {
+            query = query.xattr(&xattr.name, xattr.value.as_deref());
+        }
+    }
+    Ok(query)
+}
+
+// --------------------------------------------------
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
+// --------------------------------------------------
+// Only an argument where an option can go is rewritten: not the value of
+// the option before it, as in `--contains -name`, nor anything after `--`
+fn normalize_args<I: IntoIterator<Item = OsString>>(args: I) -> Vec<OsString> {
+    let command = Args::command();
+    let takes_value = |arg: &str| {
+        command
+            .get_arguments()
+            .filter(|opt| opt.get_action().takes_values())
+            .any(|opt| match arg.strip_prefix("--") {
+                Some(long) => opt.get_long() == Some(long),
+                None => opt
+                    .get_short()
+                    .is_some_and(|short| arg == format!("-{short}")),
+            })
+    };
+
+    let mut args = args.into_iter();
+    let mut normalized = vec![];
+    let mut is_value = false;
+    for arg in args.by_ref() {
+        let arg = match arg.to_str() {
+            Some("--") if !is_value => {
+                normalized.push(arg);
+                break;
+            }
+            Some(text) if !is_value => {
+                let text = match text.strip_prefix('-') {
+                    Some(name) if FIND_STYLE_ARGS.contains(&name) => {
+                        format!("--{name}")
+                    }
+                    _ => text.to_string(),
+                };
+                is_value = takes_value(&text);
+                text.into()
+            }
+            _ => {
+                is_value = false;
+                arg
+            }
+        };
+        normalized.push(arg);
+    }
+    normalized.extend(args);
+    normalized
+}
+
+// --------------------------------------------------
+// Clap checks a pattern as given and the query builds it again
+fn parse_glob(glob: &str) -> Result<String> {
+    build_glob(glob, false)?;
+    Ok(glob.to_string())
+}
+
+fn parse_regex(pattern: &str) -> Result<String> {
+    build_regex(pattern, false)?;
+    Ok(pattern.to_string())
+}
+
+fn parse_contents(pattern: &str) -> Result<String> {
+    bytes::Regex::new(pattern)?;
+    Ok(pattern.to_string())
+}
+
+// --------------------------------------------------
+// Parse a `find -perm` argument: an octal or symbolic mode, optionally
+// prefixed with `-` (all bits set) or `/` (any bit set).
+#[cfg(unix)]
+fn parse_perm(perm: &str) -> Result<Perm> {
+    let (mode, make): (&str, fn(u32) -> Perm) =
+        if let Some(mode) = perm.strip_prefix('-') {
+            (mode, Perm::All)
+        } else if let Some(mode) = perm.strip_prefix('/') {
+            (mode, Perm::Any)
+        } else {
+            (perm, Perm::Exact)
+        };
+
+    let bits = if !mode.is_empty() && mode.chars().all(|c| c.is_digit(8)) {
+        u32::from_str_radix(mode, 8)
+            .ok()
+            .filter(|bits| *bits <= 0o7777)
+            .ok_or_else(|| anyhow!(r#"invalid mode "{perm}""#))?
+    } else {
+        parse_symbolic_mode(mode)
+            .map_err(|_| anyhow!(r#"invalid mode "{perm}""#))?
+    };
+
+    Ok(make(bits))
+}
+
+// --------------------------------------------------
+// Apply `chmod`-style clauses such as "u=rwx,g+s,o-w" to an empty mode.
+#[cfg(unix)]
+fn parse_symbolic_mode(mode: &str) -> Result<u32> {
+    let mut bits = 0;
+    for clause in mode.split(',') {
+        let ops_start = clause
+            .find(|c| !"ugoa".contains(c))
+            .ok_or_else(|| anyhow!("missing operator"))?;
+        let (who, mut ops) = clause.split_at(ops_start);
+        let who_mask = match who.chars().fold(0, |mask, c| {
+            mask | match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                _ => 0o7777,
+            }
+        }) {
+            0 => 0o7777,
+            mask => mask,
+        };
+
+        while let Some(op) = ops.chars().next() {
+            let perms_end = ops[1..]
+                .find(|c| "+-=".contains(c))
+                .map_or(ops.len(), |i| i + 1);
+            let perm_mask =
+                ops[1..perms_end].chars().try_fold(0, |mask, c| {
+                    Ok(mask
+                        | match c {
+                            'r' => 0o444,
+                            'w' => 0o222,
+                            'x' | 'X' => 0o111,
+                            's' => 0o6000,
+                            't' => 0o1000,
+                            _ => bail!(r#"unknown permission "{c}""#),
+                        })
+                })?;
+
+            let selected = who_mask & perm_mask;
+            match op {
+                '+' => bits |= selected,
+                '-' => bits &= !selected,
+                '=' => bits = bits & !who_mask | selected,
+                _ => bail!(r#"unknown operator "{op}""#),
+            }
+            ops = &ops[perms_end..];
+        }
+    }
+
+    Ok(bits)
+}
+
+// --------------------------------------------------
+#[cfg(unix)]
+fn parse_user(user: &str) -> Result<u32> {
+    match user.parse() {
+        Ok(uid) => Ok(uid),
+        Err(_) => User::from_name(user)?
+            .map(|user| user.uid.as_raw())
+            .ok_or_else(|| anyhow!(r#"unknown user "{user}""#)),
+    }
+}
+
+// --------------------------------------------------
+#[cfg(unix)]
+fn parse_group(group: &str) -> Result<u32> {
+    match group.parse() {
+        Ok(gid) => Ok(gid),
+        Err(_) => Group::from_name(group)?
+            .map(|group| group.gid.as_raw())
+            .ok_or_else(|| anyhow!(r#"unknown group "{group}""#)),
+    }
+}
+
+// --------------------------------------------------
+#[cfg(target_os = "linux")]
+fn parse_xattr(xattr: &str) -> Result<Xattr> {
+    let (name, value) = match xattr.split_once('=') {
+        Some((name, value)) => (name, Some(value.as_bytes().to_vec())),
+        None => (xattr, None),
+    };
+    if name.is_empty() {
+        bail!(r#"invalid attribute "{xattr}""#);
+    }
+
+    Ok(Xattr {
+        name: name.to_string(),
+        value,
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod unit_tests {
+    #[cfg(unix)]
+    use super::parse_perm;
+    #[cfg(target_os = "linux")]
+    use super::{parse_xattr, Xattr};
+    #[cfg(unix)]
+    use crate::Perm;
+    #[cfg(unix)]
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_parse_perm() {
+        // Octal modes, with and without a prefix
+        assert_eq!(parse_perm("644").unwrap(), Perm::Exact(0o644));
+        assert_eq!(parse_perm("-0220").unwrap(), Perm::All(0o220));
+        assert_eq!(parse_perm("/4000").unwrap(), Perm::Any(0o4000));
+
+        // Symbolic modes
+        assert_eq!(parse_perm("u=rw,go=r").unwrap(), Perm::Exact(0o644));
+        assert_eq!(parse_perm("-g+w").unwrap(), Perm::All(0o020));
+        assert_eq!(parse_perm("/u+s,g+s").unwrap(), Perm::Any(0o6000));
+        assert_eq!(parse_perm("a+x").unwrap(), Perm::Exact(0o111));
+        assert_eq!(parse_perm("+w").unwrap(), Perm::Exact(0o222));
+        assert_eq!(parse_perm("u=rwx-x").unwrap(), Perm::Exact(0o600));
+        assert_eq!(parse_perm("o+t").unwrap(), Perm::Exact(0o1000));
+
+        // Bad modes
+        for bad in ["", "-", "8", "17777", "u", "u+q", "z+r", "u+r,"] {
+            let res = parse_perm(bad);
+            assert!(res.is_err(), "{bad}");
+            assert_eq!(
+                res.unwrap_err().to_string(),
+                format!(r#"invalid mode "{bad}""#)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_xattr() {
+        let xattr = |name: &str, value: Option<&str>| Xattr {
+            name: name.to_string(),
+            value: value.map(|value| value.as_bytes().to_vec()),
+        };
+        assert_eq!(parse_xattr("user.tag").unwrap(), xattr("user.tag", None));
+        assert_eq!(
+            parse_xattr("user.tag=a=b").unwrap(),
+            xattr("user.tag", Some("a=b"))
+        );
+        assert_eq!(
+            parse_xattr("user.tag=").unwrap(),
+            xattr("user.tag", Some(""))
+        );
+
+        let res = parse_xattr("=value");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"invalid attribute "=value""#
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::walk::{file_id, Entry};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// How much of a file the partial hash reads
+const PARTIAL_LEN: u64 = 4096;
+
+/// Files with the same contents
+#[derive(Debug)]
+pub(crate) struct Group {
+    size: u64,
+    paths: Vec<PathBuf>,
+}
+
+impl Group {
+    /// The space freed by keeping only one of the files
+    fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+// --------------------------------------------------
+/// Group the regular files among `entries` by contents. Files are first
+/// grouped by size, then by a hash of their first block, and only the
+/// files still sharing a group are hashed in full. Empty files are left
+/// out, and so are all but the first path, by name, of a file with hard
+/// links, as removing those would reclaim nothing. Hard links are only
+/// told apart on Unix.
+pub(crate) fn find_duplicates(mut entries: Vec<Entry>) -> Vec<Group> {
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    let mut inodes = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for entry in entries {
+        match entry.metadata() {
+            Err(e) => eprintln!("{}: {e}", entry.path().display()),
+            Ok(md) => {
+                if md.is_file()
+                    && md.len() > 0
+                    && file_id(&md).is_none_or(|id| inodes.insert(id))
+                {
+                    by_size
+                        .entry(md.len())
+                        .or_default()
+                        .push(entry.path().to_path_buf());
+                }
+            }
+        }
+    }
+
+    let mut groups = vec![];
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        for paths in regroup(paths, |path| hash(path, Some(PARTIAL_LEN))) {
+            let paths = if size <= PARTIAL_LEN {
+                vec![paths]
+            } else {
+                regroup(paths, |path| hash(path, None))
+            };
+            for mut paths in paths {
+                paths.sort();
+                groups.push(Group { size, paths });
+            }
+        }
+    }
+
+    // Biggest savings first
+    groups.sort_by(|a, b| {
+        b.reclaimable()
+            .cmp(&a.reclaimable())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups
+}
+
+// --------------------------------------------------
+/// Print each group under a line giving its size, then the total
+pub(crate) fn print_groups(
+    mut out: impl Write,
+    groups: &[Group],
+) -> Result<()> {
+    let mut total = 0;
+    for group in groups {
+        writeln!(
+            out,
+            "{} bytes x {} files ({} bytes reclaimable)",
+            group.size,
+            group.paths.len(),
+            group.reclaimable()
+        )?;
+        for path in &group.paths {
+            writeln!(out, "{}", path.display())?;
+        }
+        writeln!(out)?;
+        total += group.reclaimable();
+    }
+    writeln!(out, "{} groups, {total} bytes reclaimable", groups.len())?;
+    out.flush()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+/// Split `paths` by `key`, dropping the groups left with a single path and
+/// the paths whose key can't be computed
+fn regroup<K: Eq + Hash>(
+    paths: Vec<PathBuf>,
+    key: impl Fn(&PathBuf) -> io::Result<K>,
+) -> Vec<Vec<PathBuf>> {
+    let mut groups: HashMap<K, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match key(&path) {
+            Err(e) => eprintln!("{}: {e}", path.display()),
+            Ok(key) => groups.entry(key).or_default().push(path),
+        }
+    }
+    groups
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
+
+// --------------------------------------------------
+/// Hash the first `limit` bytes of a file, or all of it
+fn hash(path: &Path, limit: Option<u64>) -> io::Result<blake3::Hash> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    match limit {
+        Some(limit) => io::copy(&mut (&mut file).take(limit), &mut hasher)?,
+        None => io::copy(&mut file, &mut hasher)?,
+    };
+    Ok(hasher.finalize())
+}
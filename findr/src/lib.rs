@@ -1,105 +1,299 @@
-use anyhow::{anyhow, Result};
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
-use clap::{builder::PossibleValue, ArgAction, Parser, ValueEnum};
-use regex::Regex;
-use walkdir::{DirEntry, WalkDir};
-
-#[derive(Debug, Parser)]
-#[command(author, version, about)]
-/// Rust version of `find`
-struct Args {
-    /// Search path(s)
-    #[arg(value_name = "PATH", default_value = ".")]
-    paths: Vec<String>,
-
-    /// Names
-    #[arg(
-        short('n'),
-        long("name"),
-        value_name = "NAME",
-        value_parser(Regex::new),
-        action(ArgAction::Append),
-        num_args(0..)
-    )]
-    names: Vec<Regex>,
-
-    /// Entry types
-    #[arg(
-        short('t'),
-        long("type"),
-        value_name = "TYPE",
-        value_parser(clap::value_parser!(EntryType)),
-        action(ArgAction::Append),
-        num_args(0..)
-    )]
-    entry_types: Vec<EntryType>,
-}
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+#[cfg(target_os = "linux")]
+use nix::sys::stat::makedev;
+pub use query::{FindQuery, Matches, Predicate};
+use regex::{bytes, Regex, RegexBuilder};
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+use std::fs::{File, FileType};
+use std::io::{BufRead, BufReader};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+pub use walk::Entry;
+
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+mod duplicates;
+#[cfg(feature = "cli")]
+mod output;
+mod query;
+#[cfg(unix)]
+mod unix;
+mod walk;
+#[cfg(all(feature = "cli", target_os = "linux"))]
+mod watch;
+// --watch takes inotify, which only Linux has
+#[cfg(all(feature = "cli", not(target_os = "linux")))]
+#[path = "no_watch.rs"]
+mod watch;
 
+#[cfg(feature = "cli")]
+pub use cli::run;
+
+/// A kind of file system entry
 #[derive(Debug, Eq, PartialEq, Clone)]
-enum EntryType {
+pub enum EntryType {
     Dir,
     File,
     Link,
+    Block,
+    Char,
+    Fifo,
+    Socket,
 }
 
-impl ValueEnum for EntryType {
-    fn value_variants<'a>() -> &'a [Self] {
-        &[EntryType::Dir, EntryType::File, EntryType::Link]
+impl EntryType {
+    fn is_match(&self, file_type: &FileType) -> bool {
+        match self {
+            EntryType::Dir => file_type.is_dir(),
+            EntryType::File => file_type.is_file(),
+            EntryType::Link => file_type.is_symlink(),
+            #[cfg(unix)]
+            EntryType::Block => file_type.is_block_device(),
+            #[cfg(unix)]
+            EntryType::Char => file_type.is_char_device(),
+            #[cfg(unix)]
+            EntryType::Fifo => file_type.is_fifo(),
+            #[cfg(unix)]
+            EntryType::Socket => file_type.is_socket(),
+            // Only Unix has these
+            #[cfg(not(unix))]
+            EntryType::Block
+            | EntryType::Char
+            | EntryType::Fifo
+            | EntryType::Socket => false,
+        }
     }
+}
 
-    fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
-        Some(match self {
-            EntryType::Dir => PossibleValue::new("d"),
-            EntryType::File => PossibleValue::new("f"),
-            EntryType::Link => PossibleValue::new("l"),
-        })
+#[cfg(unix)]
+/// Permission bits to look for, as `-perm` takes them
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Perm {
+    /// Exactly these bits
+    Exact(u32),
+    /// All of these bits, and maybe others
+    All(u32),
+    /// Any of these bits, or any mode when there are none
+    Any(u32),
+}
+
+#[cfg(unix)]
+impl Perm {
+    fn is_match(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match *self {
+            Perm::Exact(bits) => mode == bits,
+            Perm::All(bits) => mode & bits == bits,
+            Perm::Any(bits) => bits == 0 || mode & bits != 0,
+        }
     }
 }
 
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+// Entries are opened by path, so a file named `-` is just that file
+fn open(path: &Path) -> Result<Box<dyn BufRead>> {
+    Ok(Box::new(BufReader::new(File::open(path)?)))
+}
+
+// --------------------------------------------------
+// Stops at the first matching line. Like `grep`, a NUL byte in the first
+// block read, or in any line, means a binary file, which never matches.
+fn has_line_matching(path: &Path, patterns: &[bytes::Regex]) -> Result<bool> {
+    let mut file = open(path)?;
+    if file.fill_buf()?.contains(&0) {
+        return Ok(false);
+    }
+
+    let mut line = vec![];
+    loop {
+        line.clear();
+        let bytes = file.read_until(b'\n', &mut line)?;
+        if bytes == 0 || line.contains(&0) {
+            return Ok(false);
+        }
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        if patterns.iter().any(|re| re.is_match(text)) {
+            return Ok(true);
+        }
     }
 }
 
-pub fn run() -> Result<()> {
-    let args = Args::parse();
-    let type_filter = |entry: &DirEntry| {
-        args.entry_types.is_empty()
-            || args.entry_types.iter().any(|entry_type| match entry_type {
-                EntryType::Link => entry.file_type().is_symlink(),
-                EntryType::Dir => entry.file_type().is_dir(),
-                EntryType::File => entry.file_type().is_file(),
-            })
-    };
-
-    let name_filter = |entry: &DirEntry| {
-        args.names.is_empty()
-            || args
-                .names
-                .iter()
-                .any(|re| re.is_match(&entry.file_name().to_string_lossy()))
-    };
-
-    for path in &args.paths {
-        let entries = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| match e {
-                Err(e) => {
-                    eprintln!("{e}");
-                    None
-                }
-                Ok(entry) => Some(entry),
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string())
-            .collect::<Vec<_>>();
-
-        println!("{}", entries.join("\n"));
+// --------------------------------------------------
+// A glob as in `find -name`: `*` and `?` also match `/` and a leading `.`.
+fn build_glob(glob: &str, insensitive: bool) -> Result<GlobMatcher> {
+    Ok(GlobBuilder::new(glob)
+        .case_insensitive(insensitive)
+        .backslash_escape(true)
+        .build()?
+        .compile_matcher())
+}
+
+// --------------------------------------------------
+// A regex as in `find -regex`: it has to match the whole path, not a part.
+fn build_regex(pattern: &str, insensitive: bool) -> Result<Regex> {
+    // Validate the pattern as given so errors don't show the anchors
+    RegexBuilder::new(pattern).build()?;
+    Ok(RegexBuilder::new(&format!("^(?:{pattern})$"))
+        .case_insensitive(insensitive)
+        .build()?)
+}
+
+// --------------------------------------------------
+// Map device numbers to the types of the file systems mounted there. The
+// fields of a mountinfo line are "ID PARENT-ID MAJOR:MINOR ROOT MOUNT-POINT
+// OPTIONS [OPTIONAL-FIELDS...] - TYPE SOURCE SUPER-OPTIONS".
+#[cfg(target_os = "linux")]
+fn parse_mountinfo(mountinfo: &str) -> HashMap<u64, String> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (mount, super_block) = line.split_once(" - ")?;
+            let (major, minor) = mount.split(' ').nth(2)?.split_once(':')?;
+            let fs_type = super_block.split(' ').next()?;
+            let dev = makedev(major.parse().ok()?, minor.parse().ok()?);
+            Some((dev, fs_type.to_string()))
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod unit_tests {
+    #[cfg(target_os = "linux")]
+    use super::parse_mountinfo;
+    #[cfg(unix)]
+    use super::Perm;
+    use super::{Entry, EntryType, FindQuery};
+    #[cfg(target_os = "linux")]
+    use nix::sys::stat::makedev;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    #[cfg(unix)]
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_perm_is_match() {
+        assert!(Perm::Exact(0o644).is_match(0o100644));
+        assert!(!Perm::Exact(0o644).is_match(0o100664));
+        assert!(Perm::All(0o220).is_match(0o664));
+        assert!(!Perm::All(0o220).is_match(0o644));
+        assert!(Perm::Any(0o022).is_match(0o624));
+        assert!(!Perm::Any(0o022).is_match(0o644));
+        assert!(Perm::Any(0).is_match(0o600));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_parse_mountinfo() {
+        let mountinfo = "\
+22 1 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+25 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw,errors=remount-ro
+40 25 0:35 / /mnt/nfs rw,relatime shared:30 master:1 - nfs4 srv:/ rw
+bad line
+";
+        let fs_types = parse_mountinfo(mountinfo);
+        assert_eq!(fs_types.len(), 3);
+        assert_eq!(fs_types[&makedev(0, 21)], "proc");
+        assert_eq!(fs_types[&makedev(8, 1)], "ext4");
+        assert_eq!(fs_types[&makedev(0, 35)], "nfs4");
+    }
+
+    #[test]
+    fn test_find_query() {
+        let mut query = FindQuery::new()
+            .path("tests/inputs/a")
+            .path("tests/inputs/d")
+            .sort_by_name(true)
+            .entry_type(EntryType::File)
+            .entry_type(EntryType::Link)
+            .name("*.csv")
+            .unwrap()
+            .name("*.mp3")
+            .unwrap()
+            .filter(|entry: &Entry| entry.depth() > 1);
+        let paths: Vec<_> = query
+            .matches()
+            .map(|entry| entry.unwrap().path().display().to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "tests/inputs/a/b/b.csv",
+                "tests/inputs/a/b/c/c.mp3",
+                "tests/inputs/d/e/e.mp3"
+            ]
+        );
+
+        // A search path that isn't there comes back as an error
+        let mut query = FindQuery::new().path("tests/inputs/nope");
+        let res: Vec<_> = query.matches().collect();
+        assert_eq!(res.len(), 1);
+        assert!(res[0].is_err());
+
+        assert!(FindQuery::new().name("a[b").is_err());
+        assert!(FindQuery::new().regex("(").is_err());
+    }
+
+    #[test]
+    fn test_find_query_ignore_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".ignore"), "{a\n").unwrap();
+        fs::write(dir.path().join("file"), "").unwrap();
+
+        // A bad ignore file is reported, and the walk goes on without it
+        for threads in [1, 4] {
+            let mut query = FindQuery::new()
+                .path(dir.path())
+                .threads(threads)
+                .gitignore(true)
+                .entry_type(EntryType::File);
+            let (errors, entries): (Vec<_>, Vec<_>) =
+                query.matches().partition(Result::is_err);
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("unclosed alternate group"));
+            assert_eq!(entries.len(), 2);
+        }
     }
 
-    Ok(())
+    #[test]
+    #[cfg(unix)]
+    fn test_find_query_metadata() {
+        let paths = |mut query: FindQuery| -> Vec<String> {
+            query
+                .matches()
+                .map(|entry| entry.unwrap().path().display().to_string())
+                .collect()
+        };
+
+        // The link's target is missing, so it is a link either way
+        let query = FindQuery::new()
+            .path("tests/inputs/d")
+            .link_target_type(EntryType::Link);
+        assert_eq!(paths(query), ["tests/inputs/d/b.csv"]);
+
+        let inode = fs::metadata("tests/inputs/a/a.txt").unwrap().ino();
+        let query = FindQuery::new()
+            .path("tests/inputs")
+            .inum(inode)
+            .perm(Perm::All(0o400))
+            .readable(true);
+        assert_eq!(paths(query), ["tests/inputs/a/a.txt"]);
+
+        let query = FindQuery::new()
+            .path("tests/inputs/a")
+            .same_file("tests/inputs/a/a.txt")
+            .unwrap()
+            .perm(Perm::Exact(0));
+        assert!(paths(query).is_empty());
+        assert!(FindQuery::new().same_file("tests/inputs/nope").is_err());
+    }
 }
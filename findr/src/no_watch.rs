@@ -0,0 +1,35 @@
+use crate::output::{Format, PathStyle};
+use crate::walk::{Entry, WalkOptions};
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Stands in for the inotify watcher where there is no inotify, and can't
+/// be made
+pub(crate) enum Watcher {}
+
+impl Watcher {
+    pub(crate) fn new(
+        _format: Format,
+        _path_style: PathStyle,
+        _opts: &WalkOptions,
+    ) -> Result<Self> {
+        bail!("--watch is only supported on Linux")
+    }
+
+    pub(crate) fn add_walked(&mut self, _dirs: Vec<(PathBuf, usize)>) {
+        match *self {}
+    }
+
+    pub(crate) fn add_match(&mut self, _entry: &Entry) {
+        match *self {}
+    }
+
+    pub(crate) fn run(
+        self,
+        _out: impl Write,
+        _is_match: impl FnMut(&Entry) -> bool,
+    ) -> Result<()> {
+        match self {}
+    }
+}
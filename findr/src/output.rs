@@ -0,0 +1,335 @@
+use crate::walk::Entry;
+use crate::EntryType;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+#[cfg(unix)]
+use nix::unistd::{Uid, User};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, ValueEnum)]
+pub(crate) enum Format {
+    /// One path per line
+    Text,
+    /// A JSON array of entry objects
+    Json,
+    /// One JSON entry object per line
+    Ndjson,
+    /// Comma-separated entry fields under a header row
+    Csv,
+}
+
+/// How to print the path of an entry
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub(crate) enum PathStyle {
+    /// Starting with the search path as given
+    AsFound,
+    /// Relative to the search path, which is `.` itself
+    Relative,
+    /// Absolute, with `.`, `..` and links above the search path resolved
+    Absolute,
+}
+
+/// Writes paths in a [`PathStyle`]
+pub(crate) struct PathFormatter {
+    style: PathStyle,
+    // Search paths made absolute
+    roots: HashMap<PathBuf, PathBuf>,
+}
+
+impl PathFormatter {
+    pub(crate) fn new(style: PathStyle) -> Self {
+        PathFormatter {
+            style,
+            roots: HashMap::new(),
+        }
+    }
+
+    /// The path of an entry `depth` levels below its search path
+    pub(crate) fn format<'p>(
+        &mut self,
+        path: &'p Path,
+        depth: usize,
+    ) -> Cow<'p, Path> {
+        if self.style == PathStyle::AsFound {
+            return Cow::Borrowed(path);
+        }
+
+        // The depth tells how many components come after the search path
+        let components: Vec<_> = path.components().collect();
+        let (root, below) = components.split_at(components.len() - depth);
+        let below: PathBuf = below.iter().collect();
+        if self.style == PathStyle::Relative {
+            return Cow::Owned(if below.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                below
+            });
+        }
+
+        let root: PathBuf = root.iter().collect();
+        let absolute = self
+            .roots
+            .entry(root)
+            .or_insert_with_key(|root| absolute(root));
+        // Joining nothing would add a trailing slash
+        Cow::Owned(if below.as_os_str().is_empty() {
+            absolute.clone()
+        } else {
+            absolute.join(below)
+        })
+    }
+}
+
+/// What the structured formats report about an entry
+#[derive(Debug, Serialize)]
+struct Record {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+    size: u64,
+    mode: String,
+    owner: String,
+    mtime: String,
+    depth: usize,
+    target: Option<String>,
+}
+
+enum Sink<W: Write> {
+    Text(W),
+    Json { out: W, empty: bool },
+    Ndjson(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+/// Writes the matching entries in the chosen format
+pub(crate) struct Printer<W: Write> {
+    sink: Sink<W>,
+    flush_lines: bool,
+    paths: PathFormatter,
+    owners: HashMap<u32, String>,
+}
+
+impl<W: Write> Printer<W> {
+    pub(crate) fn new(
+        format: Format,
+        path_style: PathStyle,
+        out: W,
+        flush_lines: bool,
+    ) -> Self {
+        let sink = match format {
+            Format::Text => Sink::Text(out),
+            Format::Json => Sink::Json { out, empty: true },
+            Format::Ndjson => Sink::Ndjson(out),
+            Format::Csv => Sink::Csv(Box::new(csv::Writer::from_writer(out))),
+        };
+
+        Printer {
+            sink,
+            flush_lines,
+            paths: PathFormatter::new(path_style),
+            owners: HashMap::new(),
+        }
+    }
+
+    /// Write an entry. One whose metadata can't be read is reported and
+    /// left out of the structured formats.
+    pub(crate) fn print(&mut self, entry: &Entry) -> Result<()> {
+        let path = self.paths.format(entry.path(), entry.depth());
+        if let Sink::Text(out) = &mut self.sink {
+            writeln!(out, "{}", path.display())?;
+        } else {
+            let record = match record(entry, &path, &mut self.owners) {
+                Ok(record) => record,
+                Err(e) => {
+                    eprintln!("{}: {e}", entry.path().display());
+                    return Ok(());
+                }
+            };
+
+            match &mut self.sink {
+                Sink::Text(_) => unreachable!(),
+                Sink::Json { out, empty } => {
+                    out.write_all(if *empty { b"[\n" } else { b",\n" })?;
+                    *empty = false;
+                    serde_json::to_writer(&mut *out, &record)
+                        .map_err(io::Error::from)?;
+                }
+                Sink::Ndjson(out) => {
+                    serde_json::to_writer(&mut *out, &record)
+                        .map_err(io::Error::from)?;
+                    out.write_all(b"\n")?;
+                }
+                Sink::Csv(wtr) => {
+                    wtr.serialize(&record).map_err(io::Error::from)?
+                }
+            }
+        }
+
+        if self.flush_lines {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Close off the output after the last entry
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if let Sink::Json { out, empty } = &mut self.sink {
+            out.write_all(if *empty { b"[]\n" } else { b"\n]\n" })?;
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.sink {
+            Sink::Text(out) | Sink::Json { out, .. } | Sink::Ndjson(out) => {
+                out.flush()?
+            }
+            Sink::Csv(wtr) => wtr.flush()?,
+        }
+        Ok(())
+    }
+}
+
+// --------------------------------------------------
+fn record(
+    entry: &Entry,
+    path: &Path,
+    owners: &mut HashMap<u32, String>,
+) -> io::Result<Record> {
+    let md = entry.metadata()?;
+    let (mode, owner) = mode_and_owner(&md, owners);
+    let target = if entry.path_is_symlink() {
+        Some(fs::read_link(entry.path())?.display().to_string())
+    } else {
+        None
+    };
+
+    Ok(Record {
+        path: path.display().to_string(),
+        entry_type: EntryType::of(&entry.file_type())
+            .map_or("?", |entry_type| entry_type.letter()),
+        size: md.len(),
+        mode,
+        owner,
+        mtime: DateTime::<Utc>::from(md.modified()?)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        depth: entry.depth(),
+        target,
+    })
+}
+
+// --------------------------------------------------
+// The permission bits in octal, and the name of the owner or its UID when
+// it has none
+#[cfg(unix)]
+fn mode_and_owner(
+    md: &Metadata,
+    owners: &mut HashMap<u32, String>,
+) -> (String, String) {
+    let owner = owners
+        .entry(md.uid())
+        .or_insert_with(|| match User::from_uid(Uid::from_raw(md.uid())) {
+            Ok(Some(user)) => user.name,
+            _ => md.uid().to_string(),
+        });
+    (format!("{:04o}", md.mode() & 0o7777), owner.clone())
+}
+
+// Other systems have neither
+#[cfg(not(unix))]
+fn mode_and_owner(
+    _md: &Metadata,
+    _owners: &mut HashMap<u32, String>,
+) -> (String, String) {
+    (String::new(), String::new())
+}
+
+// --------------------------------------------------
+// Resolve what leads up to a search path, but not the search path itself,
+// which may be a link that isn't followed. A path that can't be resolved
+// stays as it is.
+fn absolute(root: &Path) -> PathBuf {
+    let resolved = match (root.parent(), root.file_name()) {
+        (Some(parent), Some(name)) if parent.as_os_str().is_empty() => {
+            fs::canonicalize(".").map(|dir| dir.join(name))
+        }
+        (Some(parent), Some(name)) => {
+            fs::canonicalize(parent).map(|dir| dir.join(name))
+        }
+        _ => fs::canonicalize(root),
+    };
+    resolved.unwrap_or_else(|_| root.to_path_buf())
+}
+
+// --------------------------------------------------
+/// Counts and total sizes of matches, by type and by extension
+#[derive(Debug, Default)]
+pub(crate) struct Summary {
+    types: BTreeMap<usize, (u64, u64)>,
+    // Directories aren't counted here, and `None` is no extension
+    extensions: BTreeMap<Option<String>, (u64, u64)>,
+    total: (u64, u64),
+}
+
+impl Summary {
+    pub(crate) fn add(&mut self, entry: &Entry) {
+        // An entry whose size can't be read is still counted, and so is a
+        // directory, but only the sizes of what it holds add up
+        let file_type = entry.file_type();
+        let size = if file_type.is_dir() {
+            0
+        } else {
+            entry.metadata().map_or(0, |md| md.len())
+        };
+        let add = |(count, bytes): &mut (u64, u64)| {
+            *count += 1;
+            *bytes += size;
+        };
+
+        if let Some(index) = EntryType::value_variants()
+            .iter()
+            .position(|entry_type| entry_type.is_match(&file_type))
+        {
+            add(self.types.entry(index).or_default());
+        }
+        if !file_type.is_dir() {
+            let extension = entry
+                .path()
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned());
+            add(self.extensions.entry(extension).or_default());
+        }
+        add(&mut self.total);
+    }
+
+    /// Print a tab-separated table per grouping, then the totals
+    pub(crate) fn print(&self, mut out: impl Write) -> Result<()> {
+        writeln!(out, "type\tcount\tbytes")?;
+        for (index, (count, bytes)) in &self.types {
+            let entry_type = &EntryType::value_variants()[*index];
+            writeln!(out, "{}\t{count}\t{bytes}", entry_type.letter())?;
+        }
+
+        writeln!(out, "\nextension\tcount\tbytes")?;
+        // Files without an extension go last
+        let (none, some): (Vec<_>, Vec<_>) =
+            self.extensions.iter().partition(|(ext, _)| ext.is_none());
+        for (ext, (count, bytes)) in some.into_iter().chain(none) {
+            let ext = ext.as_deref().unwrap_or("(none)");
+            writeln!(out, "{ext}\t{count}\t{bytes}")?;
+        }
+
+        let (count, bytes) = self.total;
+        writeln!(out, "\ntotal\t{count}\t{bytes}")?;
+        out.flush()?;
+        Ok(())
+    }
+}
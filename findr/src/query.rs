@@ -0,0 +1,378 @@
+#[cfg(unix)]
+use crate::unix::UnixTests;
+use crate::walk::{cmp_contents_first, walk, Entry, WalkOptions};
+use crate::{build_glob, build_regex, has_line_matching, EntryType};
+use anyhow::{anyhow, Result};
+use globset::GlobMatcher;
+use regex::{bytes, Regex};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A test an entry has to pass to be a match
+pub trait Predicate {
+    fn is_match(&mut self, entry: &Entry) -> bool;
+}
+
+impl<F: FnMut(&Entry) -> bool> Predicate for F {
+    fn is_match(&mut self, entry: &Entry) -> bool {
+        self(entry)
+    }
+}
+
+/// What to search and which entries to keep, as `findr` takes it from the
+/// command line. Adding the same kind of test more than once allows more
+/// alternatives, while different kinds of tests all have to pass:
+///
+/// ```no_run
+/// use findr::{EntryType, FindQuery};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut query = FindQuery::new()
+///     .path("src")
+///     .entry_type(EntryType::File)
+///     .name("*.rs")?
+///     .name("*.toml")?
+///     .gitignore(true);
+/// for entry in query.matches() {
+///     println!("{}", entry?.path().display());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FindQuery<'a> {
+    paths: Vec<PathBuf>,
+    pub(crate) opts: WalkOptions,
+    entry_types: Vec<EntryType>,
+    link_target_types: Vec<EntryType>,
+    names: Vec<GlobMatcher>,
+    inames: Vec<GlobMatcher>,
+    path_globs: Vec<GlobMatcher>,
+    ipath_globs: Vec<GlobMatcher>,
+    regexes: Vec<Regex>,
+    iregexes: Vec<Regex>,
+    #[cfg(unix)]
+    pub(crate) unix: UnixTests,
+    contents: Vec<bytes::Regex>,
+    predicates: Vec<Box<dyn Predicate + 'a>>,
+}
+
+impl Default for FindQuery<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> FindQuery<'a> {
+    /// A query matching everything below the current directory
+    pub fn new() -> Self {
+        FindQuery {
+            paths: vec![],
+            opts: WalkOptions {
+                follow_links: false,
+                follow_root_links: false,
+                sort_by_name: false,
+                threads: 1,
+                gitignore: false,
+                skip_hidden: false,
+                same_file_system: false,
+                contents_first: false,
+            },
+            entry_types: vec![],
+            link_target_types: vec![],
+            names: vec![],
+            inames: vec![],
+            path_globs: vec![],
+            ipath_globs: vec![],
+            regexes: vec![],
+            iregexes: vec![],
+            #[cfg(unix)]
+            unix: UnixTests::new(),
+            contents: vec![],
+            predicates: vec![],
+        }
+    }
+
+    /// Add a search path
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Follow every symbolic link, as `-L` does
+    pub fn follow_links(mut self, yes: bool) -> Self {
+        self.opts.follow_links = yes;
+        self.opts.follow_root_links |= yes;
+        self
+    }
+
+    /// Follow search paths that are symbolic links, as `-H` does
+    pub fn follow_root_links(mut self, yes: bool) -> Self {
+        self.opts.follow_root_links = yes;
+        self
+    }
+
+    /// Visit the entries of each directory in name order
+    pub fn sort_by_name(mut self, yes: bool) -> Self {
+        self.opts.sort_by_name = yes;
+        self
+    }
+
+    /// Read directories with this many threads, 0 for one per CPU
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.opts.threads = threads;
+        self
+    }
+
+    /// Leave out what `.gitignore`, `.ignore` and global excludes ignore
+    pub fn gitignore(mut self, yes: bool) -> Self {
+        self.opts.gitignore = yes;
+        self
+    }
+
+    /// Leave out entries whose names start with a dot
+    pub fn skip_hidden(mut self, yes: bool) -> Self {
+        self.opts.skip_hidden = yes;
+        self
+    }
+
+    /// Don't descend into directories on other file systems
+    pub fn same_file_system(mut self, yes: bool) -> Self {
+        self.opts.same_file_system = yes;
+        self
+    }
+
+    /// List the contents of each directory before the directory itself
+    pub fn contents_first(mut self, yes: bool) -> Self {
+        self.opts.contents_first = yes;
+        self
+    }
+
+    /// Keep entries of this type
+    pub fn entry_type(mut self, entry_type: EntryType) -> Self {
+        self.entry_types.push(entry_type);
+        self
+    }
+
+    /// Keep entries of this type, looking at the target of a symbolic link
+    /// that isn't followed and at the link itself if it is, as `-xtype` does
+    pub fn link_target_type(mut self, entry_type: EntryType) -> Self {
+        self.link_target_types.push(entry_type);
+        self
+    }
+
+    /// Keep entries whose base name matches a glob
+    pub fn name(mut self, glob: &str) -> Result<Self> {
+        self.names.push(build_glob(glob, false)?);
+        Ok(self)
+    }
+
+    /// Keep entries whose base name matches a glob, ignoring case
+    pub fn iname(mut self, glob: &str) -> Result<Self> {
+        self.inames.push(build_glob(glob, true)?);
+        Ok(self)
+    }
+
+    /// Keep entries whose path matches a glob
+    pub fn path_glob(mut self, glob: &str) -> Result<Self> {
+        self.path_globs.push(build_glob(glob, false)?);
+        Ok(self)
+    }
+
+    /// Keep entries whose path matches a glob, ignoring case
+    pub fn ipath_glob(mut self, glob: &str) -> Result<Self> {
+        self.ipath_globs.push(build_glob(glob, true)?);
+        Ok(self)
+    }
+
+    /// Keep entries whose whole path matches a regular expression
+    pub fn regex(mut self, pattern: &str) -> Result<Self> {
+        self.regexes.push(build_regex(pattern, false)?);
+        Ok(self)
+    }
+
+    /// Keep entries whose whole path matches a regular expression,
+    /// ignoring case
+    pub fn iregex(mut self, pattern: &str) -> Result<Self> {
+        self.iregexes.push(build_regex(pattern, true)?);
+        Ok(self)
+    }
+
+    /// Keep regular text files with a line matching a regular expression
+    pub fn contains(mut self, pattern: &str) -> Result<Self> {
+        self.contents.push(bytes::Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Keep entries that pass a test of your own as well
+    pub fn filter(mut self, predicate: impl Predicate + 'a) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Whether an entry passes every test: the cheap ones on the entry
+    /// itself first, then those on its metadata, then the predicates in the
+    /// order they were added, and last the contents, which reads the file.
+    /// A file whose contents can't be read doesn't match.
+    pub fn is_match(&mut self, entry: &Entry) -> bool {
+        self.try_match(entry).unwrap_or(false)
+    }
+
+    /// Like [`is_match`](Self::is_match), but a file whose contents can't
+    /// be read is an error
+    pub fn try_match(&mut self, entry: &Entry) -> Result<bool> {
+        if !self.entry_match(entry) {
+            return Ok(false);
+        }
+        self.contents_match(entry)
+    }
+
+    // Every test but the contents
+    fn entry_match(&mut self, entry: &Entry) -> bool {
+        let name = entry.file_name().to_string_lossy();
+        let path = entry.path().to_string_lossy();
+        let any_glob = |globs: &[GlobMatcher], value: &str| {
+            globs.is_empty() || globs.iter().any(|glob| glob.is_match(value))
+        };
+        let any_regex = |regexes: &[Regex]| {
+            regexes.is_empty() || regexes.iter().any(|re| re.is_match(&path))
+        };
+
+        (self.entry_types.is_empty()
+            || self
+                .entry_types
+                .iter()
+                .any(|entry_type| entry_type.is_match(&entry.file_type())))
+            && any_glob(&self.names, &name)
+            && any_glob(&self.inames, &name)
+            && any_glob(&self.path_globs, &path)
+            && any_glob(&self.ipath_globs, &path)
+            && any_regex(&self.regexes)
+            && any_regex(&self.iregexes)
+            && self.link_target_match(entry)
+            && self.unix_match(entry)
+            && self
+                .predicates
+                .iter_mut()
+                .all(|predicate| predicate.is_match(entry))
+    }
+
+    // The opposite view of a symbolic link to the one the entry type gets:
+    // the target when links aren't followed, the link itself when they are.
+    // A dangling link stays a link either way.
+    fn link_target_match(&self, entry: &Entry) -> bool {
+        if self.link_target_types.is_empty() {
+            return true;
+        }
+
+        let file_type = if !entry.path_is_symlink() {
+            Ok(entry.file_type())
+        } else if entry.file_type().is_symlink() {
+            fs::metadata(entry.path())
+                .or_else(|_| fs::symlink_metadata(entry.path()))
+                .map(|md| md.file_type())
+        } else {
+            fs::symlink_metadata(entry.path()).map(|md| md.file_type())
+        };
+        file_type.is_ok_and(|file_type| {
+            self.link_target_types
+                .iter()
+                .any(|entry_type| entry_type.is_match(&file_type))
+        })
+    }
+
+    #[cfg(unix)]
+    fn unix_match(&mut self, entry: &Entry) -> bool {
+        self.unix.is_match(entry)
+    }
+
+    // Other systems have no tests of their own
+    #[cfg(not(unix))]
+    fn unix_match(&mut self, _entry: &Entry) -> bool {
+        true
+    }
+
+    fn contents_match(&self, entry: &Entry) -> Result<bool> {
+        if self.contents.is_empty() {
+            return Ok(true);
+        }
+        if !entry.file_type().is_file() {
+            return Ok(false);
+        }
+
+        has_line_matching(entry.path(), &self.contents)
+            .map_err(|e| anyhow!("{}: {e}", entry.path().display()))
+    }
+
+    /// Every entry below one search path, matching or not
+    pub(crate) fn walk(
+        &self,
+        path: &Path,
+    ) -> Box<dyn Iterator<Item = Result<Entry>>> {
+        let entries = walk(path, &self.opts);
+        // Threads finish directories in any order, so sorting by name or
+        // putting contents first has to wait for the whole walk. Comparing
+        // paths component by component gives the same order as a sorted
+        // serial walk.
+        let opts = &self.opts;
+        if opts.threads != 1 && (opts.sort_by_name || opts.contents_first) {
+            let cmp_paths = if opts.contents_first {
+                cmp_contents_first
+            } else {
+                |a: &Path, b: &Path| a.cmp(b)
+            };
+            let mut entries: Vec<_> = entries.collect();
+            entries.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => cmp_paths(a.path(), b.path()),
+                (a, b) => a.is_ok().cmp(&b.is_ok()),
+            });
+            Box::new(entries.into_iter())
+        } else {
+            entries
+        }
+    }
+
+    /// The matches below each search path in turn, or below the current
+    /// directory without any, along with the errors met on the way
+    pub fn matches(&mut self) -> Matches<'_, 'a> {
+        let paths = if self.paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.paths.clone()
+        };
+
+        Matches {
+            query: self,
+            paths: paths.into_iter(),
+            entries: None,
+        }
+    }
+}
+
+/// The iterator [`FindQuery::matches`] returns
+pub struct Matches<'q, 'a> {
+    query: &'q mut FindQuery<'a>,
+    paths: std::vec::IntoIter<PathBuf>,
+    entries: Option<Box<dyn Iterator<Item = Result<Entry>>>>,
+}
+
+impl Iterator for Matches<'_, '_> {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entries) = &mut self.entries {
+                for res in entries.by_ref() {
+                    match res {
+                        Ok(entry) => match self.query.try_match(&entry) {
+                            Ok(false) => {}
+                            Ok(true) => return Some(Ok(entry)),
+                            Err(e) => return Some(Err(e)),
+                        },
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+            }
+            self.entries = Some(self.query.walk(&self.paths.next()?));
+        }
+    }
+}
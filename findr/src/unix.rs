@@ -0,0 +1,281 @@
+#[cfg(target_os = "linux")]
+use crate::parse_mountinfo;
+use crate::walk::Entry;
+use crate::{FindQuery, Perm};
+use anyhow::{anyhow, Result};
+use nix::unistd::{access, AccessFlags, Gid, Group, Uid, User};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// The tests a [`FindQuery`] makes on the metadata only Unix has
+pub(crate) struct UnixTests {
+    // Device and inode numbers
+    same_files: Vec<(u64, u64)>,
+    inodes: Vec<u64>,
+    #[cfg(target_os = "linux")]
+    fs_types: Vec<String>,
+    // File system types by device number, read with the first type asked for
+    #[cfg(target_os = "linux")]
+    mounts: HashMap<u64, String>,
+    perms: Vec<Perm>,
+    users: Vec<u32>,
+    groups: Vec<u32>,
+    no_user: bool,
+    no_group: bool,
+    // Whether an ID has a name, looked up once each
+    known_uids: HashMap<u32, bool>,
+    known_gids: HashMap<u32, bool>,
+    access: AccessFlags,
+    #[cfg(target_os = "linux")]
+    xattrs: Vec<(String, Option<Vec<u8>>)>,
+    #[cfg(target_os = "linux")]
+    has_acl: bool,
+    #[cfg(target_os = "linux")]
+    capabilities: bool,
+}
+
+impl UnixTests {
+    pub(crate) fn new() -> Self {
+        UnixTests {
+            same_files: vec![],
+            inodes: vec![],
+            #[cfg(target_os = "linux")]
+            fs_types: vec![],
+            #[cfg(target_os = "linux")]
+            mounts: HashMap::new(),
+            perms: vec![],
+            users: vec![],
+            groups: vec![],
+            no_user: false,
+            no_group: false,
+            known_uids: HashMap::new(),
+            known_gids: HashMap::new(),
+            access: AccessFlags::empty(),
+            #[cfg(target_os = "linux")]
+            xattrs: vec![],
+            #[cfg(target_os = "linux")]
+            has_acl: false,
+            #[cfg(target_os = "linux")]
+            capabilities: false,
+        }
+    }
+
+    /// Whether an entry passes every test that was asked for
+    pub(crate) fn is_match(&mut self, entry: &Entry) -> bool {
+        self.inode_match(entry)
+            && self.fs_type_match(entry)
+            && self.perm_match(entry)
+            && self.owner_match(entry)
+            && (self.access.is_empty()
+                || access(entry.path(), self.access).is_ok())
+            && self.xattr_match(entry)
+    }
+
+    fn inode_match(&self, entry: &Entry) -> bool {
+        if self.same_files.is_empty() && self.inodes.is_empty() {
+            return true;
+        }
+
+        entry.metadata().is_ok_and(|md| {
+            (self.same_files.is_empty()
+                || self.same_files.contains(&(md.dev(), md.ino())))
+                && (self.inodes.is_empty() || self.inodes.contains(&md.ino()))
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn fs_type_match(&self, entry: &Entry) -> bool {
+        self.fs_types.is_empty()
+            || entry.metadata().is_ok_and(|md| {
+                self.mounts
+                    .get(&md.dev())
+                    .is_some_and(|fs_type| self.fs_types.contains(fs_type))
+            })
+    }
+
+    // Only Linux says which file system is mounted where
+    #[cfg(not(target_os = "linux"))]
+    fn fs_type_match(&self, _entry: &Entry) -> bool {
+        true
+    }
+
+    fn perm_match(&self, entry: &Entry) -> bool {
+        self.perms.is_empty()
+            || entry.metadata().is_ok_and(|md| {
+                self.perms.iter().any(|perm| perm.is_match(md.mode()))
+            })
+    }
+
+    fn owner_match(&mut self, entry: &Entry) -> bool {
+        if self.users.is_empty()
+            && self.groups.is_empty()
+            && !self.no_user
+            && !self.no_group
+        {
+            return true;
+        }
+
+        let Ok(md) = entry.metadata() else {
+            return false;
+        };
+        let (uid, gid) = (md.uid(), md.gid());
+        (self.users.is_empty() || self.users.contains(&uid))
+            && (self.groups.is_empty() || self.groups.contains(&gid))
+            && (!self.no_user
+                || !*self.known_uids.entry(uid).or_insert_with(|| {
+                    matches!(User::from_uid(Uid::from_raw(uid)), Ok(Some(_)))
+                }))
+            && (!self.no_group
+                || !*self.known_gids.entry(gid).or_insert_with(|| {
+                    matches!(Group::from_gid(Gid::from_raw(gid)), Ok(Some(_)))
+                }))
+    }
+
+    // Attributes are read off a link itself unless it was followed
+    #[cfg(target_os = "linux")]
+    fn xattr_match(&self, entry: &Entry) -> bool {
+        if self.xattrs.is_empty() && !self.has_acl && !self.capabilities {
+            return true;
+        }
+
+        let followed =
+            entry.path_is_symlink() && !entry.file_type().is_symlink();
+        let get = |name: &str| {
+            if followed {
+                xattr::get_deref(entry.path(), name)
+            } else {
+                xattr::get(entry.path(), name)
+            }
+            .ok()
+            .flatten()
+        };
+        (self.xattrs.is_empty()
+            || self.xattrs.iter().any(|(name, want)| {
+                get(name).is_some_and(|value| {
+                    want.as_ref().is_none_or(|want| *want == value)
+                })
+            }))
+            && (!self.has_acl
+                || ["system.posix_acl_access", "system.posix_acl_default"]
+                    .iter()
+                    .any(|name| get(name).is_some()))
+            && (!self.capabilities || get("security.capability").is_some())
+    }
+
+    // The attributes, ACLs and capabilities asked for are Linux ones
+    #[cfg(not(target_os = "linux"))]
+    fn xattr_match(&self, _entry: &Entry) -> bool {
+        true
+    }
+}
+
+impl FindQuery<'_> {
+    /// Keep hard links to the same file as a path, which is a link itself
+    /// unless [`follow_links`](Self::follow_links) was set before
+    pub fn same_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let md = if self.opts.follow_links {
+            fs::metadata(path)
+        } else {
+            fs::symlink_metadata(path)
+        }
+        .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        self.unix.same_files.push((md.dev(), md.ino()));
+        Ok(self)
+    }
+
+    /// Keep entries with this inode number
+    pub fn inum(mut self, inode: u64) -> Self {
+        self.unix.inodes.push(inode);
+        self
+    }
+
+    /// Keep entries on a file system of this type, as
+    /// `/proc/self/mountinfo` names it
+    #[cfg(target_os = "linux")]
+    pub fn fs_type(mut self, fs_type: &str) -> Result<Self> {
+        if self.unix.fs_types.is_empty() {
+            let path = "/proc/self/mountinfo";
+            self.unix.mounts = parse_mountinfo(
+                &fs::read_to_string(path)
+                    .map_err(|e| anyhow!("{path}: {e}"))?,
+            );
+        }
+        self.unix.fs_types.push(fs_type.to_string());
+        Ok(self)
+    }
+
+    /// Keep entries whose permission bits match
+    pub fn perm(mut self, perm: Perm) -> Self {
+        self.unix.perms.push(perm);
+        self
+    }
+
+    /// Keep entries owned by this UID
+    pub fn user(mut self, uid: u32) -> Self {
+        self.unix.users.push(uid);
+        self
+    }
+
+    /// Keep entries whose group is this GID
+    pub fn group(mut self, gid: u32) -> Self {
+        self.unix.groups.push(gid);
+        self
+    }
+
+    /// Keep entries whose UID has no user
+    pub fn no_user(mut self, yes: bool) -> Self {
+        self.unix.no_user = yes;
+        self
+    }
+
+    /// Keep entries whose GID has no group
+    pub fn no_group(mut self, yes: bool) -> Self {
+        self.unix.no_group = yes;
+        self
+    }
+
+    /// Keep entries the current user can read
+    pub fn readable(mut self, yes: bool) -> Self {
+        self.unix.access.set(AccessFlags::R_OK, yes);
+        self
+    }
+
+    /// Keep entries the current user can write
+    pub fn writable(mut self, yes: bool) -> Self {
+        self.unix.access.set(AccessFlags::W_OK, yes);
+        self
+    }
+
+    /// Keep entries the current user can execute, or search for a
+    /// directory
+    pub fn executable(mut self, yes: bool) -> Self {
+        self.unix.access.set(AccessFlags::X_OK, yes);
+        self
+    }
+
+    /// Keep entries with an extended attribute, holding `value` if given
+    #[cfg(target_os = "linux")]
+    pub fn xattr(mut self, name: &str, value: Option<&[u8]>) -> Self {
+        self.unix
+            .xattrs
+            .push((name.to_string(), value.map(<[u8]>::to_vec)));
+        self
+    }
+
+    /// Keep entries with a POSIX access or default ACL
+    #[cfg(target_os = "linux")]
+    pub fn has_acl(mut self, yes: bool) -> Self {
+        self.unix.has_acl = yes;
+        self
+    }
+
+    /// Keep entries with Linux file capabilities
+    #[cfg(target_os = "linux")]
+    pub fn capabilities(mut self, yes: bool) -> Self {
+        self.unix.capabilities = yes;
+        self
+    }
+}
@@ -0,0 +1,667 @@
+use anyhow::{anyhow, Error, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::{self, FileType, Metadata};
+use std::io;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use walkdir::{DirEntry, WalkDir};
+
+/// An entry found while walking a search path
+#[derive(Debug)]
+pub struct Entry {
+    path: PathBuf,
+    file_type: FileType,
+    path_is_symlink: bool,
+    depth: usize,
+}
+
+impl Entry {
+    /// The path of the entry, starting with its search path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The last component of the path
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    /// The type of the entry, or of its target if the link was followed
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Whether the path itself is a symbolic link, followed or not
+    pub fn path_is_symlink(&self) -> bool {
+        self.path_is_symlink
+    }
+
+    /// How many directories below its search path the entry is
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Look up an entry outside of a walk, such as one a watch reports
+    #[cfg(all(feature = "cli", target_os = "linux"))]
+    pub(crate) fn from_path(
+        path: PathBuf,
+        depth: usize,
+        follow: bool,
+    ) -> io::Result<Entry> {
+        let md = fs::symlink_metadata(&path)?;
+        let entry = Entry {
+            file_type: md.file_type(),
+            path_is_symlink: md.file_type().is_symlink(),
+            depth,
+            path,
+        };
+        Ok(if follow { entry.follow() } else { entry })
+    }
+
+    /// Resolve a symbolic link to its target, leaving a dangling link as is
+    fn follow(mut self) -> Self {
+        if self.file_type.is_symlink() {
+            if let Ok(md) = fs::metadata(&self.path) {
+                self.file_type = md.file_type();
+            }
+        }
+        self
+    }
+
+    /// The metadata of the entry, or of its target if the link was followed
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        if self.path_is_symlink && !self.file_type.is_symlink() {
+            fs::metadata(&self.path)
+        } else {
+            fs::symlink_metadata(&self.path)
+        }
+    }
+}
+
+impl From<DirEntry> for Entry {
+    fn from(entry: DirEntry) -> Self {
+        Entry {
+            file_type: entry.file_type(),
+            path_is_symlink: entry.path_is_symlink(),
+            depth: entry.depth(),
+            path: entry.into_path(),
+        }
+    }
+}
+
+/// How to walk a search path
+#[derive(Debug, Clone)]
+pub(crate) struct WalkOptions {
+    /// Follow every symbolic link
+    pub follow_links: bool,
+    /// Follow a search path that is a symbolic link
+    pub follow_root_links: bool,
+    /// Visit the entries of each directory in name order
+    pub sort_by_name: bool,
+    /// Number of threads reading directories, 0 for one per CPU
+    pub threads: usize,
+    /// Leave out what `.gitignore`, `.ignore` and global excludes ignore
+    pub gitignore: bool,
+    /// Leave out entries whose names start with a dot
+    pub skip_hidden: bool,
+    /// List, but don't descend into, directories on other file systems
+    pub same_file_system: bool,
+    /// Yield the contents of a directory before the directory itself
+    pub contents_first: bool,
+}
+
+/// The ignore rules of a directory, layered over those of its parent
+pub(crate) struct Ignores {
+    matcher: Gitignore,
+    parent: Option<Arc<Ignores>>,
+    // For the rules of a directory above a search path: the search path
+    // as given and where it really is, since the rules are rooted there
+    rebase: Option<Arc<(PathBuf, PathBuf)>>,
+}
+
+impl Ignores {
+    fn matched(&self, path: &Path, is_dir: bool) -> Match<()> {
+        let matched = match self.rebase.as_deref() {
+            Some((root, real)) => match path.strip_prefix(root) {
+                Ok(rest) => self.matcher.matched(real.join(rest), is_dir),
+                Err(_) => self.matcher.matched(path, is_dir),
+            },
+            None => self.matcher.matched(path, is_dir),
+        };
+        matched.map(|_| ())
+    }
+}
+
+pub(crate) type IgnoreChain = Option<Arc<Ignores>>;
+
+/// Decides which entries the walk leaves out, together with their contents
+pub(crate) struct Pruner {
+    skip_hidden: bool,
+    // The global excludes, when honoring ignore files at all
+    global: Option<Gitignore>,
+    // Ignore files that could not be read or parsed, for the walk to report
+    errors: Mutex<Vec<Error>>,
+}
+
+impl Pruner {
+    pub(crate) fn new(opts: &WalkOptions) -> Self {
+        let mut errors = vec![];
+        let global = opts.gitignore.then(|| {
+            let (matcher, err) = Gitignore::global();
+            errors.extend(err.map(|e| anyhow!(e)));
+            matcher
+        });
+
+        Pruner {
+            skip_hidden: opts.skip_hidden,
+            global,
+            errors: Mutex::new(errors),
+        }
+    }
+
+    /// The errors met since last asked
+    pub(crate) fn take_errors(&self) -> Vec<Error> {
+        std::mem::take(&mut self.errors.lock().unwrap())
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.skip_hidden || self.global.is_some()
+    }
+
+    /// The rules a search path inherits from the directories above it, up
+    /// to the top of the git repository it is in
+    pub(crate) fn ignores_above(&self, root: &Path) -> IgnoreChain {
+        self.global.as_ref()?;
+        let real = fs::canonicalize(root).ok().filter(|real| real.is_dir())?;
+        if real.join(".git").exists() {
+            return None;
+        }
+
+        let mut dirs = vec![];
+        for dir in real.ancestors().skip(1) {
+            dirs.push(dir);
+            if dir.join(".git").exists() {
+                // Outermost first, so the nearer rules win
+                let rebase = Arc::new((root.to_path_buf(), real.clone()));
+                return dirs.into_iter().rev().fold(None, |parent, dir| {
+                    self.load(dir, parent, Some(Arc::clone(&rebase)))
+                });
+            }
+        }
+        // Not in a repository
+        None
+    }
+
+    /// The rules for the contents of `dir`, given those of its parent
+    pub(crate) fn ignores_in(
+        &self,
+        dir: &Path,
+        parent: IgnoreChain,
+    ) -> IgnoreChain {
+        // Nothing to collect unless ignore files are honored
+        self.global.as_ref()?;
+        self.load(dir, parent, None)
+    }
+
+    fn load(
+        &self,
+        dir: &Path,
+        parent: IgnoreChain,
+        rebase: Option<Arc<(PathBuf, PathBuf)>>,
+    ) -> IgnoreChain {
+        // `.ignore` comes last so that its rules win
+        let files: Vec<_> = [".gitignore", ".ignore"]
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect();
+        if files.is_empty() {
+            return parent;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut errors = vec![];
+        for file in &files {
+            errors.extend(builder.add(file).map(|e| anyhow!(e)));
+        }
+        let ignores = match builder.build() {
+            Ok(matcher) => Some(Arc::new(Ignores {
+                matcher,
+                parent,
+                rebase,
+            })),
+            Err(e) => {
+                errors.push(anyhow!(e));
+                parent
+            }
+        };
+        self.errors.lock().unwrap().extend(errors);
+        ignores
+    }
+
+    /// Whether to leave out an entry below a search path
+    pub(crate) fn is_pruned(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        ignores: &IgnoreChain,
+    ) -> bool {
+        let name = path.file_name().unwrap_or_default().as_encoded_bytes();
+        if self.skip_hidden && name.starts_with(b".") {
+            return true;
+        }
+
+        let Some(global) = &self.global else {
+            return false;
+        };
+        // Git's own directory is never part of the work tree
+        if is_dir && name == b".git" {
+            return true;
+        }
+
+        let mut next = ignores.as_deref();
+        while let Some(ignores) = next {
+            match ignores.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => next = ignores.parent.as_deref(),
+            }
+        }
+        global.matched(path, is_dir).is_ignore()
+    }
+}
+
+type Entries = Box<dyn Iterator<Item = Result<Entry>>>;
+
+// --------------------------------------------------
+pub(crate) fn walk(root: &Path, opts: &WalkOptions) -> Entries {
+    // Spotting link loops and mount points takes the device and inode
+    // numbers only Unix has, so elsewhere walkdir does it on one thread
+    if opts.threads == 1
+        || cfg!(not(unix)) && (opts.follow_links || opts.same_file_system)
+    {
+        walk_serial(root, opts)
+    } else {
+        walk_parallel(root, opts)
+    }
+}
+
+// --------------------------------------------------
+/// The device and inode numbers of a file, where there are any
+#[cfg(unix)]
+pub(crate) fn file_id(md: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn file_id(_md: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// --------------------------------------------------
+fn walk_serial(root: &Path, opts: &WalkOptions) -> Entries {
+    let mut walker = WalkDir::new(root)
+        .follow_links(opts.follow_links)
+        .follow_root_links(opts.follow_root_links)
+        .same_file_system(opts.same_file_system);
+    if opts.sort_by_name {
+        walker = walker.sort_by_file_name();
+    }
+
+    // walkdir only applies `filter_entry` to a directory as it yields it,
+    // which with `contents_first` is too late to prune what it holds, so
+    // pruning walks keep to preorder and reorder the entries afterwards
+    let pruner = Arc::new(Pruner::new(opts));
+    let post_order = opts.contents_first && pruner.is_active();
+    walker = walker.contents_first(opts.contents_first && !post_order);
+
+    let walker: Box<dyn Iterator<Item = walkdir::Result<DirEntry>>> =
+        if pruner.is_active() {
+            let above = pruner.ignores_above(root);
+            // The rules in effect inside each directory on the current path,
+            // indexed by depth: walkdir visits depth first, so the entries
+            // past an entry's own depth belong to an earlier subtree.
+            let mut ignores: Vec<IgnoreChain> = vec![];
+            let pruner = Arc::clone(&pruner);
+            Box::new(walker.into_iter().filter_entry(move |dent| {
+                let depth = dent.depth();
+                ignores.truncate(depth);
+                let parent = match ignores.last() {
+                    Some(parent) => parent.clone(),
+                    None => above.clone(),
+                };
+                let is_dir = dent.file_type().is_dir();
+                if depth > 0 && pruner.is_pruned(dent.path(), is_dir, &parent) {
+                    return false;
+                }
+                // A search path may be a link walkdir descends into
+                if is_dir || depth == 0 {
+                    ignores.push(pruner.ignores_in(dent.path(), parent));
+                }
+                true
+            }))
+        } else {
+            Box::new(walker.into_iter())
+        };
+
+    let follow_root = opts.follow_root_links;
+    let entries = Box::new(walker.flat_map(move |res| {
+        // The ignore files read on the way to this entry come first
+        let errors = pruner.take_errors().into_iter().map(Err);
+        let entry = match res {
+            Err(e) => dangling_link(&e).ok_or_else(|| anyhow!(e)),
+            // walkdir descends into a linked root but still reports it as a
+            // link, where `find -H` reports what it points to
+            Ok(entry) if entry.depth() == 0 && follow_root => {
+                Ok(Entry::from(entry).follow())
+            }
+            Ok(entry) => Ok(Entry::from(entry)),
+        };
+        errors.chain(iter::once(entry))
+    }));
+
+    if post_order {
+        Box::new(PostOrder {
+            entries,
+            dirs: vec![],
+            ready: VecDeque::new(),
+        })
+    } else {
+        entries
+    }
+}
+
+// Turns a preorder walk into a postorder one by holding each directory
+// back until the walk leaves it.
+struct PostOrder {
+    entries: Entries,
+    // The directories on the current path
+    dirs: Vec<Entry>,
+    ready: VecDeque<Result<Entry>>,
+}
+
+impl Iterator for PostOrder {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(next) = self.ready.pop_front() {
+                return Some(next);
+            }
+
+            match self.entries.next() {
+                None => return self.dirs.pop().map(Ok),
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(entry)) => {
+                    // Anything as deep as this entry holds nothing more
+                    while self
+                        .dirs
+                        .last()
+                        .is_some_and(|dir| dir.depth >= entry.depth)
+                    {
+                        self.ready.push_back(Ok(self.dirs.pop().unwrap()));
+                    }
+                    if entry.file_type.is_dir() {
+                        self.dirs.push(entry);
+                    } else {
+                        self.ready.push_back(Ok(entry));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// --------------------------------------------------
+/// Order paths component by component, with every path after all those
+/// below it, as a sorted walk yielding contents first would
+pub(crate) fn cmp_contents_first(a: &Path, b: &Path) -> Ordering {
+    let (mut a, mut b) = (a.components(), b.components());
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(a), Some(b)) => return a.cmp(&b),
+            (None, Some(_)) => return Ordering::Greater,
+            (Some(_), None) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+// --------------------------------------------------
+// When following links, walkdir reports a dangling link as an error, but
+// `find -L` lists it as a link.
+fn dangling_link(err: &walkdir::Error) -> Option<Entry> {
+    let path = err.path()?;
+    if err.io_error()?.kind() != io::ErrorKind::NotFound {
+        return None;
+    }
+
+    let md = fs::symlink_metadata(path).ok()?;
+    md.file_type().is_symlink().then(|| Entry {
+        path: path.to_path_buf(),
+        file_type: md.file_type(),
+        path_is_symlink: true,
+        depth: err.depth(),
+    })
+}
+
+// --------------------------------------------------
+// Walk with a pool of threads: every directory read is a task that spawns a
+// task per subdirectory, and idle threads steal those from busy ones, so
+// siblings are read concurrently. Entries come back in no particular order.
+fn walk_parallel(root: &Path, opts: &WalkOptions) -> Entries {
+    let (tx, rx) = mpsc::channel();
+    let root = root.to_path_buf();
+    let opts = opts.clone();
+
+    let handle = thread::spawn(move || {
+        let pool = match rayon::ThreadPoolBuilder::new()
+            .num_threads(opts.threads)
+            .build()
+        {
+            Ok(pool) => pool,
+            Err(e) => {
+                let _ = tx.send(Err(anyhow!(e)));
+                return;
+            }
+        };
+
+        let root_entry = match fs::symlink_metadata(&root) {
+            Err(e) => {
+                let _ = tx.send(Err(anyhow!("{}: {e}", root.display())));
+                return;
+            }
+            Ok(md) => Entry {
+                file_type: md.file_type(),
+                path_is_symlink: md.file_type().is_symlink(),
+                depth: 0,
+                path: root,
+            },
+        };
+        let root_entry = if opts.follow_root_links {
+            root_entry.follow()
+        } else {
+            root_entry
+        };
+
+        let root_dev = if opts.same_file_system {
+            match root_entry.metadata() {
+                Ok(md) => file_id(&md).map(|(dev, _)| dev),
+                Err(e) => {
+                    let err = anyhow!("{}: {e}", root_entry.path.display());
+                    let _ = tx.send(Err(err));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let pruner = Pruner::new(&opts);
+        let above = pruner.ignores_above(&root_entry.path);
+        for e in pruner.take_errors() {
+            let _ = tx.send(Err(e));
+        }
+        let walker = ParallelWalker {
+            opts,
+            pruner,
+            root_dev,
+            tx,
+        };
+        pool.scope(|scope| {
+            walker.visit(scope, root_entry, Arc::new(vec![]), above)
+        });
+    });
+
+    Box::new(ParallelEntries {
+        rx,
+        handle: Some(handle),
+    })
+}
+
+// Device and inode numbers of the directories above the one being read,
+// kept to spot links back up the tree when following links
+type Ancestors = Arc<Vec<(u64, u64)>>;
+
+struct ParallelWalker {
+    opts: WalkOptions,
+    pruner: Pruner,
+    // The file system to stay on, if any
+    root_dev: Option<u64>,
+    tx: mpsc::Sender<Result<Entry>>,
+}
+
+impl ParallelWalker {
+    /// Report an entry and, for a directory, queue a task to read it with
+    /// the ignore rules of the directory it is in.
+    /// Returns false once nobody is listening any more.
+    fn visit<'s>(
+        &'s self,
+        scope: &rayon::Scope<'s>,
+        entry: Entry,
+        ancestors: Ancestors,
+        ignores: IgnoreChain,
+    ) -> bool {
+        if !entry.file_type.is_dir() {
+            return self.tx.send(Ok(entry)).is_ok();
+        }
+
+        let mut ancestors = ancestors;
+        if self.opts.follow_links || self.root_dev.is_some() {
+            let id = match entry.metadata() {
+                // Only a Unix walk gets here, see `walk`
+                Ok(md) => file_id(&md).expect("device and inode numbers"),
+                Err(e) => {
+                    let err = anyhow!("{}: {e}", entry.path.display());
+                    return self.tx.send(Err(err)).is_ok();
+                }
+            };
+            // A mount point is listed, but not what is mounted there
+            if self.root_dev.is_some_and(|dev| dev != id.0) {
+                return self.tx.send(Ok(entry)).is_ok();
+            }
+            if self.opts.follow_links {
+                if ancestors.contains(&id) {
+                    let err = anyhow!(
+                        "File system loop found: {} points to an ancestor",
+                        entry.path.display()
+                    );
+                    return self.tx.send(Err(err)).is_ok();
+                }
+                let mut chain = ancestors.to_vec();
+                chain.push(id);
+                ancestors = Arc::new(chain);
+            }
+        }
+
+        let (dir, depth) = (entry.path.clone(), entry.depth);
+        if self.tx.send(Ok(entry)).is_err() {
+            return false;
+        }
+        scope.spawn(move |scope| {
+            self.read_dir(scope, &dir, depth + 1, ancestors, ignores)
+        });
+        true
+    }
+
+    fn read_dir<'s>(
+        &'s self,
+        scope: &rayon::Scope<'s>,
+        dir: &Path,
+        depth: usize,
+        ancestors: Ancestors,
+        parent_ignores: IgnoreChain,
+    ) {
+        let ignores = self.pruner.ignores_in(dir, parent_ignores);
+        for e in self.pruner.take_errors() {
+            if self.tx.send(Err(e)).is_err() {
+                return;
+            }
+        }
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                let _ = self.tx.send(Err(anyhow!("{}: {e}", dir.display())));
+                return;
+            }
+        };
+
+        for dent in entries {
+            let entry = match dent.and_then(|dent| {
+                let file_type = dent.file_type()?;
+                Ok(Entry {
+                    path: dent.path(),
+                    file_type,
+                    path_is_symlink: file_type.is_symlink(),
+                    depth,
+                })
+            }) {
+                Ok(entry) if self.opts.follow_links => entry.follow(),
+                Ok(entry) => entry,
+                Err(e) => {
+                    let err = anyhow!("{}: {e}", dir.display());
+                    if self.tx.send(Err(err)).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            let is_dir = entry.file_type.is_dir();
+            if self.pruner.is_pruned(&entry.path, is_dir, &ignores) {
+                continue;
+            }
+            let ancestors = Arc::clone(&ancestors);
+            if !self.visit(scope, entry, ancestors, ignores.clone()) {
+                return;
+            }
+        }
+    }
+}
+
+struct ParallelEntries {
+    rx: mpsc::Receiver<Result<Entry>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Iterator for ParallelEntries {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.rx.recv().ok();
+        if next.is_none() {
+            if let Some(Err(e)) = self.handle.take().map(|h| h.join()) {
+                std::panic::resume_unwind(e);
+            }
+        }
+        next
+    }
+}
@@ -0,0 +1,357 @@
+use crate::output::{Format, PathFormatter, PathStyle};
+use crate::walk::{Entry, IgnoreChain, Pruner, WalkOptions};
+use crate::EntryType;
+use anyhow::Result;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl Change {
+    fn name(&self) -> &'static str {
+        match self {
+            Change::Created => "created",
+            Change::Modified => "modified",
+            Change::Deleted => "deleted",
+        }
+    }
+}
+
+/// A change as `--format ndjson` reports it
+#[derive(Debug, Serialize)]
+struct Event {
+    event: Change,
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+}
+
+/// A directory being watched
+struct Dir {
+    path: PathBuf,
+    depth: usize,
+    // The ignore rules in effect inside it
+    ignores: IgnoreChain,
+}
+
+/// Keeps reporting the entries that are created, written to or deleted
+/// in the directories a search walked
+pub(crate) struct Watcher {
+    inotify: Inotify,
+    format: Format,
+    paths: PathFormatter,
+    follow_links: bool,
+    pruner: Pruner,
+    dirs: HashMap<WatchDescriptor, Dir>,
+    // The rules of the walked directories, by path, when there are any
+    ignores: HashMap<PathBuf, IgnoreChain>,
+    // The entries that currently match, with their types and depths, so a
+    // deletion can be reported only for those
+    matches: BTreeMap<PathBuf, (&'static str, usize)>,
+}
+
+impl Watcher {
+    pub(crate) fn new(
+        format: Format,
+        path_style: PathStyle,
+        opts: &WalkOptions,
+    ) -> Result<Self> {
+        let pruner = Pruner::new(opts);
+        // The walk reported these already
+        pruner.take_errors();
+        Ok(Watcher {
+            inotify: Inotify::init()?,
+            format,
+            paths: PathFormatter::new(path_style),
+            follow_links: opts.follow_links,
+            pruner,
+            dirs: HashMap::new(),
+            ignores: HashMap::new(),
+            matches: BTreeMap::new(),
+        })
+    }
+
+    /// Watch the directories a walk found
+    pub(crate) fn add_walked(&mut self, mut dirs: Vec<(PathBuf, usize)>) {
+        // Parents first, for their ignore rules
+        dirs.sort_by_key(|(_, depth)| *depth);
+        for (path, depth) in dirs {
+            let parent = match path.parent() {
+                Some(parent) if depth > 0 => {
+                    self.ignores.get(parent).cloned().flatten()
+                }
+                _ => self.pruner.ignores_above(&path),
+            };
+            self.watch_dir(&path, depth, parent);
+        }
+        // The walk reported these already
+        self.pruner.take_errors();
+    }
+
+    /// Remember an entry the walk printed
+    pub(crate) fn add_match(&mut self, entry: &Entry) {
+        self.matches.insert(
+            entry.path().to_path_buf(),
+            (type_letter(entry), entry.depth()),
+        );
+    }
+
+    /// Report changes as they happen, for as long as anything is watched
+    pub(crate) fn run(
+        mut self,
+        mut out: impl Write,
+        mut is_match: impl FnMut(&Entry) -> bool,
+    ) -> Result<()> {
+        let mut buffer = [0; 4096];
+        while !self.dirs.is_empty() {
+            for event in self.inotify.read_events_blocking(&mut buffer)? {
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    eprintln!("Too many changes at once, some went unreported");
+                    continue;
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let (Some(dir), Some(name)) =
+                    (self.dirs.get(&event.wd), event.name)
+                else {
+                    continue;
+                };
+
+                let path = dir.path.join(name);
+                let (depth, ignores) = (dir.depth + 1, dir.ignores.clone());
+                if event
+                    .mask
+                    .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                {
+                    self.created(
+                        &mut out,
+                        path,
+                        depth,
+                        ignores,
+                        &mut is_match,
+                    )?;
+                } else if event.mask.contains(EventMask::CLOSE_WRITE) {
+                    self.modified(
+                        &mut out,
+                        path,
+                        depth,
+                        ignores,
+                        &mut is_match,
+                    )?;
+                } else {
+                    self.deleted(&mut out, &path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Start watching a directory, returning the rules for its contents if
+    /// it wasn't watched already
+    fn watch_dir(
+        &mut self,
+        path: &Path,
+        depth: usize,
+        parent: IgnoreChain,
+    ) -> Option<IgnoreChain> {
+        let mask = WatchMask::CREATE
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::EXCL_UNLINK;
+        let wd = match self.inotify.watches().add(path, mask) {
+            Ok(wd) => wd,
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                return None;
+            }
+        };
+        // Another path to the same directory, through a link
+        if self.dirs.contains_key(&wd) {
+            return None;
+        }
+
+        let ignores = self.pruner.ignores_in(path, parent);
+        if self.pruner.is_active() {
+            self.ignores.insert(path.to_path_buf(), ignores.clone());
+        }
+        let dir = Dir {
+            path: path.to_path_buf(),
+            depth,
+            ignores: ignores.clone(),
+        };
+        self.dirs.insert(wd, dir);
+        Some(ignores)
+    }
+
+    /// Report a new entry, then, for a directory, watch it and report what
+    /// it already holds
+    fn created(
+        &mut self,
+        out: &mut impl Write,
+        path: PathBuf,
+        depth: usize,
+        parent: IgnoreChain,
+        is_match: &mut impl FnMut(&Entry) -> bool,
+    ) -> io::Result<()> {
+        // Gone again already
+        let Ok(entry) = Entry::from_path(path, depth, self.follow_links) else {
+            return Ok(());
+        };
+        let is_dir = entry.file_type().is_dir();
+        if self.pruner.is_pruned(entry.path(), is_dir, &parent) {
+            return Ok(());
+        }
+
+        // Entries made just before their directory was watched can show up
+        // both in the scan below and as events
+        if is_match(&entry) && !self.matches.contains_key(entry.path()) {
+            self.add_match(&entry);
+            self.report(out, Change::Created, &entry)?;
+        }
+
+        if !is_dir {
+            return Ok(());
+        }
+        let watched = self.watch_dir(entry.path(), depth, parent);
+        for e in self.pruner.take_errors() {
+            eprintln!("{e}");
+        }
+        let Some(ignores) = watched else {
+            return Ok(());
+        };
+        let Ok(contents) = fs::read_dir(entry.path()) else {
+            return Ok(());
+        };
+        for dent in contents.flatten() {
+            self.created(
+                out,
+                dent.path(),
+                depth + 1,
+                ignores.clone(),
+                is_match,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Report a file that was written to, if it matches now
+    fn modified(
+        &mut self,
+        out: &mut impl Write,
+        path: PathBuf,
+        depth: usize,
+        parent: IgnoreChain,
+        is_match: &mut impl FnMut(&Entry) -> bool,
+    ) -> io::Result<()> {
+        let Ok(entry) = Entry::from_path(path, depth, self.follow_links) else {
+            return Ok(());
+        };
+        if self.pruner.is_pruned(entry.path(), false, &parent) {
+            return Ok(());
+        }
+
+        if is_match(&entry) {
+            self.add_match(&entry);
+            self.report(out, Change::Modified, &entry)
+        } else {
+            self.matches.remove(entry.path());
+            Ok(())
+        }
+    }
+
+    /// Report the matches at or below a path that was removed or moved away,
+    /// and stop watching the directories there
+    fn deleted(&mut self, out: &mut impl Write, path: &Path) -> io::Result<()> {
+        let gone: Vec<_> = self
+            .matches
+            .range(path.to_path_buf()..)
+            .take_while(|(match_path, _)| match_path.starts_with(path))
+            .map(|(match_path, found)| (match_path.clone(), *found))
+            .collect();
+        for (match_path, (entry_type, depth)) in gone {
+            self.matches.remove(&match_path);
+            self.write_event(
+                out,
+                Change::Deleted,
+                &match_path,
+                depth,
+                entry_type,
+            )?;
+        }
+
+        let unwatched: Vec<_> = self
+            .dirs
+            .iter()
+            .filter(|(_, dir)| dir.path.starts_with(path))
+            .map(|(wd, _)| wd.clone())
+            .collect();
+        for wd in unwatched {
+            if let Some(dir) = self.dirs.remove(&wd) {
+                self.ignores.remove(&dir.path);
+                // Fails for a directory that no longer exists
+                let _ = self.inotify.watches().remove(wd);
+            }
+        }
+        Ok(())
+    }
+
+    fn report(
+        &mut self,
+        out: &mut impl Write,
+        change: Change,
+        entry: &Entry,
+    ) -> io::Result<()> {
+        self.write_event(
+            out,
+            change,
+            entry.path(),
+            entry.depth(),
+            type_letter(entry),
+        )
+    }
+
+    // Paths are written as the walk writes them, so --relative and
+    // --absolute apply to changes as well
+    fn write_event(
+        &mut self,
+        out: &mut impl Write,
+        change: Change,
+        path: &Path,
+        depth: usize,
+        entry_type: &'static str,
+    ) -> io::Result<()> {
+        let path = self.paths.format(path, depth);
+        if self.format == Format::Ndjson {
+            let event = Event {
+                event: change,
+                path: path.display().to_string(),
+                entry_type,
+            };
+            serde_json::to_writer(&mut *out, &event)?;
+            out.write_all(b"\n")?;
+        } else {
+            writeln!(out, "{} {}", change.name(), path.display())?;
+        }
+        out.flush()
+    }
+}
+
+// --------------------------------------------------
+fn type_letter(entry: &Entry) -> &'static str {
+    EntryType::of(&entry.file_type())
+        .map_or("?", |entry_type| entry_type.letter())
+}
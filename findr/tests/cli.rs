@@ -1,9 +1,26 @@
 use anyhow::Result;
+#[cfg(not(windows))]
+use assert_cmd::cargo::cargo_bin;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
 use rand::{distributions::Alphanumeric, Rng};
+#[cfg(not(windows))]
+use std::os::unix::{
+    fs::{symlink, MetadataExt, PermissionsExt},
+    net::UnixListener,
+};
 use std::{borrow::Cow, fs, path::Path};
+#[cfg(not(windows))]
+use std::{
+    io::{BufRead, BufReader},
+    process::Stdio,
+    time::Duration,
+};
+#[cfg(target_os = "linux")]
+use std::{sync::mpsc, thread};
+#[cfg(not(windows))]
+use tempfile::TempDir;
 
 const PRG: &str = "findr";
 
@@ -39,7 +56,18 @@ fn skips_bad_dir() -> Result<()> {
 #[test]
 fn dies_bad_name() -> Result<()> {
     Command::cargo_bin(PRG)?
-        .args(["--name", "*.csv"])
+        .args(["--name", "a[b"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("error: invalid value 'a[b'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--regex", "*.csv"])
         .assert()
         .failure()
         .stderr(predicate::str::contains("error: invalid value '*.csv'"));
@@ -58,16 +86,41 @@ fn dies_bad_type() -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn dies_bad_perm() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--perm", "u+q"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"invalid mode "u+q""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn dies_unknown_user() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--user", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(r#"unknown user "{bad}""#)));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -228,7 +281,7 @@ fn type_f_l() -> Result<()> {
 #[test]
 fn name_csv() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv"],
+        &["tests/inputs", "-n", "*.csv"],
         "tests/expected/name_csv.txt",
     )
 }
@@ -237,7 +290,7 @@ fn name_csv() -> Result<()> {
 #[test]
 fn name_csv_mp3() -> Result<()> {
     run(
-        &["tests/inputs", "-n", ".*[.]csv", "-n", ".*[.]mp3"],
+        &["tests/inputs", "-n", "*.csv", "-n", "*.mp3"],
         "tests/expected/name_csv_mp3.txt",
     )
 }
@@ -246,7 +299,7 @@ fn name_csv_mp3() -> Result<()> {
 #[test]
 fn name_txt_path_a_d() -> Result<()> {
     run(
-        &["tests/inputs/a", "tests/inputs/d", "--name", ".*.txt"],
+        &["tests/inputs/a", "tests/inputs/d", "--name", "*.txt"],
         "tests/expected/name_txt_path_a_d.txt",
     )
 }
@@ -254,14 +307,14 @@ fn name_txt_path_a_d() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn name_a() -> Result<()> {
-    run(&["tests/inputs", "-n", "a"], "tests/expected/name_a.txt")
+    run(&["tests/inputs", "-n", "a*"], "tests/expected/name_a.txt")
 }
 
 // --------------------------------------------------
 #[test]
 fn type_f_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "-t", "f", "-n", "a"],
+        &["tests/inputs", "-t", "f", "-n", "a*"],
         "tests/expected/type_f_name_a.txt",
     )
 }
@@ -270,17 +323,224 @@ fn type_f_name_a() -> Result<()> {
 #[test]
 fn type_d_name_a() -> Result<()> {
     run(
-        &["tests/inputs", "--type", "d", "--name", "a"],
+        &["tests/inputs", "--type", "d", "--name", "a*"],
         "tests/expected/type_d_name_a.txt",
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn name_find_style() -> Result<()> {
+    run(
+        &["tests/inputs", "-name", "*.csv"],
+        "tests/expected/name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn iname_csv() -> Result<()> {
+    run(
+        &["tests/inputs", "--iname", "*.CSV"],
+        "tests/expected/name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn path_glob_b() -> Result<()> {
+    run(
+        &["tests/inputs", "--path", "*/b/*"],
+        "tests/expected/path_glob_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ipath_glob_b() -> Result<()> {
+    run(
+        &["tests/inputs", "-ipath", "*/B/*"],
+        "tests/expected/path_glob_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_csv() -> Result<()> {
+    run(
+        &["tests/inputs", "--regex", ".*[.]csv"],
+        "tests/expected/name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_partial() -> Result<()> {
+    run(
+        &["tests/inputs", "--regex", "csv"],
+        "tests/expected/regex_partial.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn iregex_csv() -> Result<()> {
+    run(
+        &["tests/inputs", "-iregex", ".*[.]CSV"],
+        "tests/expected/name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn xtype_f() -> Result<()> {
+    run(
+        &["tests/inputs", "--xtype", "f"],
+        "tests/expected/xtype_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn xtype_l() -> Result<()> {
+    run(
+        &["tests/inputs", "-xtype", "l"],
+        "tests/expected/xtype_l.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_type_f() -> Result<()> {
+    run(
+        &["-L", "tests/inputs", "-t", "f"],
+        "tests/expected/follow_type_f.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_type_l() -> Result<()> {
+    run(
+        &["-L", "tests/inputs", "-t", "l"],
+        "tests/expected/follow_type_l.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn samefile_b() -> Result<()> {
+    run(
+        &["tests/inputs", "-samefile", "tests/inputs/a/b/b.csv"],
+        "tests/expected/samefile_b.txt",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn path_g() -> Result<()> {
     run(&["tests/inputs/g.csv"], "tests/expected/path_g.txt")
 }
 
+// --------------------------------------------------
+#[test]
+fn parallel_path1() -> Result<()> {
+    run(&["tests/inputs", "-j", "4"], "tests/expected/path1.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn parallel_type_f_path_a_b_d() -> Result<()> {
+    run(
+        &[
+            "tests/inputs/a/b",
+            "tests/inputs/d",
+            "-j",
+            "4",
+            "--type",
+            "f",
+        ],
+        "tests/expected/type_f_path_a_b_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn parallel_follow_type_l() -> Result<()> {
+    run(
+        &["-L", "-j", "0", "tests/inputs", "-t", "l"],
+        "tests/expected/follow_type_l.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn sort_name() -> Result<()> {
+    let output = |args: &[&str]| -> Result<String> {
+        let cmd = Command::cargo_bin(PRG)?
+            .args(args)
+            .args(["--sort", "name"])
+            .assert()
+            .success();
+        Ok(String::from_utf8(cmd.get_output().stdout.clone())?)
+    };
+
+    let expected = [
+        "tests/inputs/a",
+        "tests/inputs/a/a.txt",
+        "tests/inputs/a/b",
+        "tests/inputs/a/b/b.csv",
+        "tests/inputs/a/b/c",
+        "tests/inputs/a/b/c/c.mp3",
+        "",
+    ];
+    assert_eq!(output(&["tests/inputs/a"])?, expected.join("\n"));
+    assert_eq!(output(&["tests/inputs/a", "-j", "4"])?, expected.join("\n"));
+
+    let all = ["tests/inputs", "tests/inputs/d", "-j"];
+    assert_eq!(output(&[&all[..], &["4"]].concat())?, output(&all[..2])?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_matches() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-n", "nomatch"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn closed_pipe() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for i in 0..10_000 {
+        fs::write(dir.path().join(i.to_string()), "")?;
+    }
+
+    for jobs in ["1", "4"] {
+        let mut child = std::process::Command::new(cargo_bin(PRG))
+            .arg(dir.path())
+            .args(["-j", jobs])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut first = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut first)?;
+        assert_eq!(first.trim_end(), dir.path().display().to_string());
+
+        let out = child.wait_with_output()?;
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8(out.stderr)?, "");
+    }
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 #[cfg(not(windows))]
@@ -312,3 +572,1105 @@ fn unreadable_dir() -> Result<()> {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn perm_tree() -> Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    for (name, mode) in [
+        ("exe", 0o755),
+        ("shared", 0o664),
+        ("private", 0o600),
+        ("setuid", 0o4755),
+    ] {
+        let path = dir.path().join(name);
+        fs::write(&path, "")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn run_names(dir: &Path, args: &[&str], expected: &[&str]) -> Result<()> {
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir)
+        .args(args)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut names: Vec<_> = stdout
+        .lines()
+        .filter(|s| !s.is_empty())
+        .map(|line| Path::new(line).file_name().unwrap().to_string_lossy())
+        .collect();
+    names.sort();
+
+    assert_eq!(names, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm_exact() -> Result<()> {
+    let dir = perm_tree()?;
+    run_names(dir.path(), &["-t", "f", "--perm", "600"], &["private"])?;
+    run_names(dir.path(), &["-t", "f", "-perm", "u=rw,go=r"], &[])?;
+    run_names(dir.path(), &["-t", "f", "-perm", "ug=rw,o=r"], &["shared"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm_all() -> Result<()> {
+    let dir = perm_tree()?;
+    run_names(dir.path(), &["-t", "f", "--perm", "-664"], &["shared"])?;
+    run_names(
+        dir.path(),
+        &["-t", "f", "-perm", "-u+x"],
+        &["exe", "setuid"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm_any() -> Result<()> {
+    let dir = perm_tree()?;
+    run_names(dir.path(), &["-t", "f", "--perm", "/4000"], &["setuid"])?;
+    run_names(dir.path(), &["-t", "f", "-perm", "/g+w,o+w"], &["shared"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn user_and_group() -> Result<()> {
+    let dir = perm_tree()?;
+    let md = fs::metadata(dir.path())?;
+    let (uid, gid) = (md.uid().to_string(), md.gid().to_string());
+    let all = ["exe", "private", "setuid", "shared"];
+    run_names(dir.path(), &["-t", "f", "--user", &uid], &all)?;
+    run_names(dir.path(), &["-t", "f", "-group", &gid], &all)?;
+    run_names(dir.path(), &["-t", "f", "-user", &uid, "-nouser"], &[])?;
+    run_names(dir.path(), &["-t", "f", "--nogroup"], &[])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn executable() -> Result<()> {
+    let dir = perm_tree()?;
+    run_names(dir.path(), &["-t", "f", "--executable"], &["exe", "setuid"])?;
+    run_names(
+        dir.path(),
+        &["-t", "f", "-readable", "-writable"],
+        &["exe", "private", "setuid", "shared"],
+    )
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn link_tree() -> Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("real"))?;
+    fs::write(dir.path().join("real/file"), "")?;
+    symlink("real", dir.path().join("to_real"))?;
+    symlink("real/file", dir.path().join("to_file"))?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn follow_modes() -> Result<()> {
+    let dir = link_tree()?;
+    run_names(dir.path(), &["-t", "l"], &["to_file", "to_real"])?;
+    run_names(dir.path(), &["-P", "-t", "f"], &["file"])?;
+    run_names(dir.path(), &["-L", "-t", "f"], &["file", "file", "to_file"])?;
+    run_names(dir.path(), &["-L", "-t", "l"], &[])?;
+    run_names(dir.path(), &["-L", "--xtype", "l"], &["to_file", "to_real"])?;
+    run_names(
+        dir.path(),
+        &["-L", "-t", "d", "-n", "*real"],
+        &["real", "to_real"],
+    )?;
+
+    let to_real = dir.path().join("to_real");
+    run_names(&to_real, &[], &["to_real"])?;
+    run_names(&to_real, &["-H"], &["file", "to_real"])?;
+    run_names(&to_real, &["-H", "-t", "d"], &["to_real"])?;
+    run_names(&to_real, &["-L", "-P"], &["to_real"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn follow_loop() -> Result<()> {
+    let dir = link_tree()?;
+    symlink("..", dir.path().join("real/loop"))?;
+    for jobs in ["1", "4"] {
+        let cmd = Command::cargo_bin(PRG)?
+            .arg(dir.path())
+            .args(["-j", jobs, "-L", "-t", "f"])
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("File system loop found"));
+
+        let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+        assert_eq!(stdout.lines().filter(|s| !s.is_empty()).count(), 3);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn special_types() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    nix::unistd::mkfifo(
+        &dir.path().join("fifo"),
+        nix::sys::stat::Mode::S_IRWXU,
+    )?;
+    let _listener = UnixListener::bind(dir.path().join("socket"))?;
+    fs::write(dir.path().join("file"), "")?;
+
+    run_names(dir.path(), &["-t", "p"], &["fifo"])?;
+    run_names(dir.path(), &["-t", "s"], &["socket"])?;
+    run_names(dir.path(), &["-t", "p", "s"], &["fifo", "socket"])?;
+    run_names(dir.path(), &["-t", "b", "c"], &[])?;
+    run_names(Path::new("/dev/null"), &["-t", "c"], &["null"])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn hard_links() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("orig"), "")?;
+    fs::write(dir.path().join("other"), "")?;
+    fs::hard_link(dir.path().join("orig"), dir.path().join("hard"))?;
+    let orig = dir.path().join("orig");
+    let inode = fs::metadata(&orig)?.ino().to_string();
+
+    run_names(dir.path(), &["--inum", &inode], &["hard", "orig"])?;
+    run_names(
+        dir.path(),
+        &["-samefile", orig.to_str().unwrap()],
+        &["hard", "orig"],
+    )
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn repo_tree() -> Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    for sub in ["src", "target/debug", ".git", ".hidden"] {
+        fs::create_dir_all(dir.path().join(sub))?;
+    }
+    for (file, contents) in [
+        (".gitignore", "target/\n*.log\n!keep.log\n"),
+        (".ignore", "secret.txt\n"),
+        ("src/.gitignore", "gen.rs\n"),
+        ("src/gen.rs", ""),
+        ("src/main.rs", ""),
+        ("target/debug/app", ""),
+        ("build.log", ""),
+        ("keep.log", ""),
+        ("secret.txt", ""),
+        ("old.bak", ""),
+        (".git/HEAD", ""),
+        (".hidden/file", ""),
+        (".env", ""),
+    ] {
+        fs::write(dir.path().join(file), contents)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore() -> Result<()> {
+    let dir = repo_tree()?;
+    for jobs in ["1", "4"] {
+        run_names(
+            dir.path(),
+            &["-j", jobs, "--gitignore", "-t", "f"],
+            &[
+                ".env",
+                ".gitignore",
+                ".gitignore",
+                ".ignore",
+                "file",
+                "keep.log",
+                "main.rs",
+                "old.bak",
+            ],
+        )?;
+        run_names(
+            dir.path(),
+            &["-j", jobs, "--gitignore", "-t", "d"],
+            &[
+                ".hidden",
+                dir.path().file_name().unwrap().to_str().unwrap(),
+                "src",
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore_above_search_path() -> Result<()> {
+    let dir = repo_tree()?;
+    fs::write(dir.path().join(".ignore"), "secret.txt\n/src/local/\n")?;
+    for sub in ["src/target", "src/local"] {
+        fs::create_dir(dir.path().join(sub))?;
+    }
+    fs::write(dir.path().join("src/debug.log"), "")?;
+
+    let expected = [".gitignore", "main.rs", "src"];
+    for jobs in ["1", "4"] {
+        run_names(
+            &dir.path().join("src"),
+            &["-j", jobs, "--gitignore"],
+            &expected,
+        )?;
+        // The rules above a relative search path apply just the same
+        let cmd = Command::cargo_bin(PRG)?
+            .current_dir(dir.path())
+            .args(["src", "-j", jobs, "--gitignore", "--sort", "name"])
+            .assert()
+            .success();
+        assert_eq!(
+            String::from_utf8(cmd.get_output().stdout.clone())?,
+            "src\nsrc/.gitignore\nsrc/main.rs\n"
+        );
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore_bad_file() -> Result<()> {
+    let dir = repo_tree()?;
+    fs::write(dir.path().join("src/.gitignore"), "{gen.rs\n")?;
+    for jobs in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .arg(dir.path().join("src"))
+            .args(["-j", jobs, "--gitignore", "-t", "f"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("gen.rs"))
+            .stderr(predicate::str::contains("unclosed alternate group"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn gitignore_global_excludes() -> Result<()> {
+    let dir = repo_tree()?;
+    let config = tempfile::tempdir()?;
+    fs::create_dir(config.path().join("git"))?;
+    fs::write(config.path().join("git/ignore"), "*.bak\n")?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["--gitignore", "-n", "*.bak"])
+        .env("HOME", config.path())
+        .env("XDG_CONFIG_HOME", config.path())
+        .assert()
+        .success();
+    assert_eq!(String::from_utf8(cmd.get_output().stdout.clone())?, "");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn no_hidden() -> Result<()> {
+    let dir = repo_tree()?;
+    for jobs in ["1", "4"] {
+        run_names(
+            dir.path(),
+            &["-j", jobs, "--no-hidden", "-t", "f"],
+            &[
+                "app",
+                "build.log",
+                "gen.rs",
+                "keep.log",
+                "main.rs",
+                "old.bak",
+                "secret.txt",
+            ],
+        )?;
+        run_names(
+            dir.path(),
+            &["-j", jobs, "--no-hidden", "--gitignore", "-t", "f"],
+            &["keep.log", "main.rs", "old.bak"],
+        )?;
+    }
+    run_names(
+        &dir.path().join(".hidden"),
+        &["--no-hidden"],
+        &[".hidden", "file"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn contains() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("unsafe.d"))?;
+    for (file, contents) in [
+        ("a.rs", "fn main() {\n    unsafe { work() }\n}\n"),
+        ("b.rs", "fn main() {}\n"),
+        ("c.txt", "unsafe\n"),
+        ("d.bin", "\0\nunsafe\n"),
+        ("e.rs", "// UNSAFE\n"),
+    ] {
+        fs::write(dir.path().join(file), contents)?;
+    }
+
+    run_names(dir.path(), &["--contains", "unsafe"], &["a.rs", "c.txt"])?;
+    run_names(
+        dir.path(),
+        &["-contains", "(?i)unsafe"],
+        &["a.rs", "c.txt", "e.rs"],
+    )?;
+    run_names(
+        dir.path(),
+        &["-type", "f", "-name", "*.rs", "-contains", "unsafe"],
+        &["a.rs"],
+    )?;
+    run_names(
+        dir.path(),
+        &["--contains", "^}$", "--contains", "^un"],
+        &["a.rs", "c.txt"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn contains_find_style_pattern() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("opts.txt"), "findr -name '*.rs'\n")?;
+    fs::write(dir.path().join("name.txt"), "name\n")?;
+
+    // A pattern that looks like an option is still the pattern
+    run_names(dir.path(), &["--contains", "-name"], &["opts.txt"])?;
+    run_names(
+        dir.path(),
+        &["-contains", "-name", "-name", "*.txt"],
+        &["opts.txt"],
+    )?;
+    run_names(dir.path(), &["-name", "-name"], &[])
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn contains_odd_names() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("-"), "unsafe\n")?;
+    let name = OsStr::from_bytes(b"\xe9.rs");
+    fs::write(dir.path().join(name), "unsafe\n")?;
+
+    // A search path named `-` is a file, not stdin
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["-", "--contains", "unsafe"])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("-\n");
+
+    // Names that aren't UTF-8 are still opened
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-name", "*.rs", "--contains", "unsafe"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(".rs\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_contains() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--contains", "("])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("error: invalid value '('"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn format_tree() -> Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    fs::create_dir(root.join("sub"))?;
+    fs::write(root.join("sub/file.txt"), "hello\n")?;
+    fs::set_permissions(
+        root.join("sub/file.txt"),
+        fs::Permissions::from_mode(0o640),
+    )?;
+    symlink("sub/file.txt", root.join("link"))?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn format_ndjson() -> Result<()> {
+    let dir = format_tree()?;
+    let output = Command::cargo_bin(PRG)?
+        .args([dir.path().to_str().unwrap(), "--format", "ndjson"])
+        .args(["--sort", "name"])
+        .output()?;
+    assert!(output.status.success());
+
+    let records = String::from_utf8(output.stdout)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let summary: Vec<_> = records
+        .iter()
+        .map(|r| (r["type"].as_str().unwrap(), r["depth"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(summary, [("d", 0), ("l", 1), ("d", 1), ("f", 2)]);
+
+    let link = &records[1];
+    assert_eq!(link["target"], "sub/file.txt");
+    let file = &records[3];
+    assert!(file["path"].as_str().unwrap().ends_with("sub/file.txt"));
+    assert_eq!(file["size"], 6);
+    assert_eq!(file["mode"], "0640");
+    assert_eq!(file["target"], serde_json::Value::Null);
+    assert!(file["owner"].is_string());
+    assert!(file["mtime"].as_str().unwrap().ends_with('Z'));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn format_json() -> Result<()> {
+    let dir = format_tree()?;
+    let output = Command::cargo_bin(PRG)?
+        .args([
+            dir.path().to_str().unwrap(),
+            "-type",
+            "f",
+            "--format",
+            "json",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(records.as_array().unwrap().len(), 1);
+    assert_eq!(records[0]["type"], "f");
+
+    Command::cargo_bin(PRG)?
+        .args([dir.path().to_str().unwrap(), "--name", "nothing"])
+        .args(["--format", "json"])
+        .assert()
+        .success()
+        .stdout("[]\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn format_csv() -> Result<()> {
+    let dir = format_tree()?;
+    let output = Command::cargo_bin(PRG)?
+        .args([dir.path().to_str().unwrap(), "--format", "csv"])
+        .args(["--type", "l"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines[0], "path,type,size,mode,owner,mtime,depth,target");
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains(",l,"));
+    assert!(lines[1].ends_with(",1,sub/file.txt"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn duplicates() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    fs::create_dir(root.join("x"))?;
+    let big = vec![0; 10_000];
+    let mut big_diff = big.clone();
+    big_diff[9_999] = 1;
+    for (file, contents) in [
+        ("a.txt", &b"same\n"[..]),
+        ("x/b.txt", b"same\n"),
+        ("c.txt", b"diff\n"),
+        ("big1.bin", &big),
+        ("x/big2.bin", &big),
+        ("big3.bin", &big_diff),
+        ("empty1", b""),
+        ("empty2", b""),
+    ] {
+        fs::write(root.join(file), contents)?;
+    }
+    fs::hard_link(root.join("a.txt"), root.join("z.txt"))?;
+    let dir_path = root.to_str().unwrap();
+    let path = |file: &str| root.join(file).display().to_string();
+
+    let expected = [
+        "10000 bytes x 2 files (10000 bytes reclaimable)".to_string(),
+        path("big1.bin"),
+        path("x/big2.bin"),
+        "".to_string(),
+        "5 bytes x 2 files (5 bytes reclaimable)".to_string(),
+        path("a.txt"),
+        path("x/b.txt"),
+        "".to_string(),
+        "2 groups, 10005 bytes reclaimable".to_string(),
+        "".to_string(),
+    ];
+    Command::cargo_bin(PRG)?
+        .args([dir_path, "--duplicates"])
+        .assert()
+        .success()
+        .stdout(expected.join("\n"));
+
+    // The filters narrow down the candidates
+    Command::cargo_bin(PRG)?
+        .args([dir_path, "--duplicates", "-name", "*.txt"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 groups, 5 bytes reclaimable"));
+    Command::cargo_bin(PRG)?
+        .args([dir_path, "--duplicates", "-type", "d"])
+        .assert()
+        .success()
+        .stdout("0 groups, 0 bytes reclaimable\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_duplicates_conflicts() -> Result<()> {
+    for args in [
+        &["--format", "csv"][..],
+        &["--sort", "size"],
+        &["--reverse"],
+        &["--limit", "1"],
+    ] {
+        Command::cargo_bin(PRG)?
+            .args([".", "--duplicates"])
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn xdev() -> Result<()> {
+    // File systems mounted under /dev, such as /dev/pts and /dev/shm
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    let mount_points: Vec<_> = mountinfo
+        .lines()
+        .filter_map(|line| line.split(' ').nth(4))
+        .filter(|mount_point| mount_point.starts_with("/dev/"))
+        .collect();
+
+    for jobs in ["1", "4"] {
+        for flag in ["-xdev", "-mount"] {
+            let output = Command::cargo_bin(PRG)?
+                .args(["/dev", flag, "-j", jobs])
+                .output()?;
+            let stdout = String::from_utf8(output.stdout)?;
+            let lines: Vec<_> = stdout.lines().collect();
+            assert!(lines.contains(&"/dev"));
+            for mount_point in &mount_points {
+                let inside = format!("{mount_point}/");
+                assert!(lines.contains(mount_point), "{mount_point}");
+                assert!(!lines.iter().any(|line| line.starts_with(&inside)));
+            }
+        }
+    }
+
+    // Nothing to cross below the inputs
+    run(&["tests/inputs", "-xdev"], "tests/expected/path1.txt")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn fstype() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["/proc/self/fdinfo", "-fstype", "proc", "-type", "d"])
+        .assert()
+        .success()
+        .stdout("/proc/self/fdinfo\n");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-fstype", "proc"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+// Start a watch and read its output on another thread, so that a missing
+// event fails the test rather than hanging it
+#[cfg(target_os = "linux")]
+fn watch_lines(
+    cmd: &mut std::process::Command,
+) -> Result<(std::process::Child, impl FnMut() -> String)> {
+    let mut child = cmd.stdout(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+    let next_line =
+        move || rx.recv_timeout(Duration::from_secs(10)).expect("no event");
+    Ok((child, next_line))
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    fs::create_dir(root.join("sub"))?;
+    fs::write(root.join("sub/seed.txt"), "")?;
+    fs::write(root.join("skip.rs"), "")?;
+    let path = |file: &str| root.join(file).display().to_string();
+    let text = |event: &str, file: &str| format!("{event} {}", path(file));
+    let ndjson = |event: &str, file: &str| {
+        format!(
+            r#"{{"event":"{event}","path":"{}","type":"f"}}"#,
+            path(file)
+        )
+    };
+
+    for (format, line) in [
+        ("text", &text as &dyn Fn(&str, &str) -> String),
+        ("ndjson", &ndjson),
+    ] {
+        let (mut child, mut next_line) = watch_lines(
+            std::process::Command::new(cargo_bin(PRG))
+                .arg(root)
+                .args(["--watch", "--format", format, "-type", "f"])
+                .args(["-name", "*.txt"]),
+        )?;
+
+        // The walk comes first, and only then the changes
+        assert!(next_line().contains(&path("sub/seed.txt")));
+
+        fs::write(root.join("new.txt"), "")?;
+        assert_eq!(next_line(), line("created", "new.txt"));
+        assert_eq!(next_line(), line("modified", "new.txt"));
+
+        // The file may be written before its new directory is watched
+        fs::write(root.join("other.rs"), "")?;
+        fs::create_dir_all(root.join("a/b"))?;
+        fs::write(root.join("a/b/deep.txt"), "")?;
+        assert_eq!(next_line(), line("created", "a/b/deep.txt"));
+
+        fs::remove_file(root.join("new.txt"))?;
+        let mut next = next_line();
+        if next == line("modified", "a/b/deep.txt") {
+            next = next_line();
+        }
+        assert_eq!(next, line("deleted", "new.txt"));
+        fs::remove_dir_all(root.join("a"))?;
+        assert_eq!(next_line(), line("deleted", "a/b/deep.txt"));
+
+        child.kill()?;
+        child.wait()?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch_sorted() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    fs::write(root.join("a.txt"), "")?;
+    fs::write(root.join("b.txt"), "")?;
+    let path = |file: &str| root.join(file).display().to_string();
+
+    for args in [&["--sort", "path"][..], &["--reverse"]] {
+        let (mut child, mut next_line) = watch_lines(
+            std::process::Command::new(cargo_bin(PRG))
+                .arg(root)
+                .args(["--watch", "-type", "f"])
+                .args(args),
+        )?;
+
+        // The sorted walk still leaves its directories watched
+        let walked = [next_line(), next_line()];
+        assert!(walked.contains(&path("a.txt")));
+        assert!(walked.contains(&path("b.txt")));
+        fs::write(root.join("c.txt"), "")?;
+        assert_eq!(next_line(), format!("created {}", path("c.txt")));
+
+        child.kill()?;
+        child.wait()?;
+        fs::remove_file(root.join("c.txt"))?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch_path_style() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path().canonicalize()?;
+    fs::create_dir(root.join("sub"))?;
+    fs::write(root.join("seed.txt"), "")?;
+
+    for (style, prefix) in [
+        ("--relative", String::new()),
+        ("--absolute", format!("{}/", root.display())),
+    ] {
+        let (mut child, mut next_line) = watch_lines(
+            std::process::Command::new(cargo_bin(PRG))
+                .current_dir(&root)
+                .args([".", "--watch", style, "-type", "f"]),
+        )?;
+
+        // The walk comes first, and only then the changes
+        assert_eq!(next_line(), format!("{prefix}seed.txt"));
+        fs::write(root.join("sub/new.txt"), "")?;
+        assert_eq!(next_line(), format!("created {prefix}sub/new.txt"));
+        assert_eq!(next_line(), format!("modified {prefix}sub/new.txt"));
+        fs::remove_file(root.join("sub/new.txt"))?;
+        assert_eq!(next_line(), format!("deleted {prefix}sub/new.txt"));
+
+        child.kill()?;
+        child.wait()?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_watch_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([".", "--watch", "--format", "csv"])
+        .assert()
+        .failure()
+        .stderr("--watch prints text or ndjson\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn sized_tree() -> Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir(dir.path().join("sub"))?;
+    // Sizes and times in a different order than the names
+    for (file, size, age) in [
+        ("a.log", 30, 1),
+        ("b.txt", 10, 3),
+        ("c.txt", 20, 2),
+        ("sub/d", 40, 4),
+    ] {
+        let path = dir.path().join(file);
+        fs::write(&path, "x".repeat(size))?;
+        let mtime =
+            std::time::SystemTime::now() - Duration::from_secs(age * 3600);
+        fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(mtime)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn sort_size_mtime() -> Result<()> {
+    let dir = sized_tree()?;
+    for jobs in ["1", "4"] {
+        for (args, expected) in [
+            (
+                &["--sort", "size"][..],
+                &["b.txt", "c.txt", "a.log", "d"][..],
+            ),
+            (
+                &["--sort", "size", "--reverse"],
+                &["d", "a.log", "c.txt", "b.txt"],
+            ),
+            (
+                &["--sort", "size", "--reverse", "--limit", "2"],
+                &["d", "a.log"],
+            ),
+            (&["--sort", "mtime"], &["d", "b.txt", "c.txt", "a.log"]),
+            (
+                &["--sort", "mtime", "--reverse", "--limit", "1"],
+                &["a.log"],
+            ),
+        ] {
+            let output = Command::cargo_bin(PRG)?
+                .arg(dir.path())
+                .args(["-type", "f", "-j", jobs])
+                .args(args)
+                .output()?;
+            assert!(output.status.success());
+            let names: Vec<_> = String::from_utf8(output.stdout)?
+                .lines()
+                .map(|line| {
+                    Path::new(line)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            assert_eq!(names, expected, "{args:?}");
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_path() -> Result<()> {
+    // Unlike `--sort name`, the order spans the search paths
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/d", "tests/inputs/a", "--sort", "path"])
+        .args(["-t", "f", "-n", "*.txt"])
+        .assert()
+        .success()
+        .stdout("tests/inputs/a/a.txt\ntests/inputs/d/d.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn limit() -> Result<()> {
+    for (limit, lines) in [("0", 0), ("3", 3), ("100", 12)] {
+        let output = Command::cargo_bin(PRG)?
+            .args(["tests/inputs/a", "tests/inputs/d", "--limit", limit])
+            .output()?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout)?.lines().count(), lines);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn summary() -> Result<()> {
+    let dir = sized_tree()?;
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .args(["-type", "f", "l", "--summary"])
+        .assert()
+        .success()
+        .stdout(
+            "type\tcount\tbytes\nf\t4\t100\n\n\
+            extension\tcount\tbytes\nlog\t1\t30\ntxt\t2\t30\n(none)\t1\t40\n\n\
+            total\t4\t100\n",
+        );
+    // Directories are counted, but their own sizes aren't
+    Command::cargo_bin(PRG)?
+        .arg(dir.path())
+        .arg("--summary")
+        .assert()
+        .success()
+        .stdout(
+            "type\tcount\tbytes\nd\t2\t0\nf\t4\t100\n\n\
+            extension\tcount\tbytes\nlog\t1\t30\ntxt\t2\t30\n(none)\t1\t40\n\n\
+            total\t6\t100\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn depth_first() -> Result<()> {
+    let expected = [
+        "tests/inputs/a/a.txt",
+        "tests/inputs/a/b/b.csv",
+        "tests/inputs/a/b/c/c.mp3",
+        "tests/inputs/a/b/c",
+        "tests/inputs/a/b",
+        "tests/inputs/a",
+        "",
+    ]
+    .join("\n");
+
+    for jobs in ["1", "4"] {
+        // Pruning walks put contents first on their own
+        for args in
+            [&["-depth"][..], &["-d"], &["--depth-first", "--no-hidden"]]
+        {
+            Command::cargo_bin(PRG)?
+                .args(["tests/inputs/a", "--sort", "name", "-j", jobs])
+                .args(args)
+                .assert()
+                .success()
+                .stdout(expected.clone());
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn relative() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a", "--relative", "--sort", "name"])
+        .assert()
+        .success()
+        .stdout(".\na.txt\nb\nb/b.csv\nb/c\nb/c/c.mp3\n");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/", "tests/inputs/d/", "--relative"])
+        .args(["-name", "*.txt"])
+        .assert()
+        .success()
+        .stdout("a.txt\nd.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn absolute() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = fs::canonicalize(dir.path())?;
+    fs::create_dir(root.join("sub"))?;
+    fs::write(root.join("sub/file"), "")?;
+    symlink("sub", root.join("link"))?;
+
+    Command::cargo_bin(PRG)?
+        .arg(root.join("sub/../sub/"))
+        .args(["--absolute", "--sort", "name"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{}\n{}\n",
+            root.join("sub").display(),
+            root.join("sub/file").display()
+        ));
+
+    // A link given as the search path stays a link
+    Command::cargo_bin(PRG)?
+        .current_dir(&root)
+        .args(["link", "--absolute"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", root.join("link").display()));
+    Command::cargo_bin(PRG)?
+        .current_dir(&root)
+        .args(["-H", "link", "--absolute", "-type", "f"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", root.join("link/file").display()));
+
+    Command::cargo_bin(PRG)?
+        .args([".", "--absolute", "--relative"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn xattrs() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for file in ["tagged", "other", "acl", "setcap", "plain"] {
+        fs::write(dir.path().join(file), "")?;
+    }
+    let set = |file: &str, name: &str, value: &[u8]| {
+        xattr::set(dir.path().join(file), name, value)
+    };
+    if set("tagged", "user.tag", b"secret").is_err() {
+        eprintln!("Skipping: no user attributes on this file system");
+        return Ok(());
+    }
+    set("other", "user.tag", b"public")?;
+    set("other", "user.note", b"")?;
+
+    run_names(dir.path(), &["-xattr", "user.tag"], &["other", "tagged"])?;
+    run_names(dir.path(), &["-xattr", "user.tag=secret"], &["tagged"])?;
+    run_names(
+        dir.path(),
+        &["-xattr", "user.tag=secret", "-xattr", "user.note"],
+        &["other", "tagged"],
+    )?;
+    run_names(dir.path(), &["-xattr", "user.tag=", "-type", "f"], &[])?;
+
+    // user::rw-, user:1000:r--, group::r--, mask::r--, other::r--
+    let mut acl = 2u32.to_le_bytes().to_vec();
+    for (tag, perm, id) in [
+        (0x01u16, 6u16, u32::MAX),
+        (0x02, 4, 1000),
+        (0x04, 4, u32::MAX),
+        (0x10, 4, u32::MAX),
+        (0x20, 4, u32::MAX),
+    ] {
+        acl.extend(tag.to_le_bytes());
+        acl.extend(perm.to_le_bytes());
+        acl.extend(id.to_le_bytes());
+    }
+    if set("acl", "system.posix_acl_access", &acl).is_ok() {
+        run_names(dir.path(), &["-has-acl"], &["acl"])?;
+    }
+
+    // Revision 2, effective, with CAP_NET_RAW permitted
+    let mut caps = 0x0200_0001u32.to_le_bytes().to_vec();
+    for word in [1u32 << 13, 0, 0, 0] {
+        caps.extend(word.to_le_bytes());
+    }
+    if set("setcap", "security.capability", &caps).is_ok() {
+        run_names(dir.path(), &["-capabilities"], &["setcap"])?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn dies_bad_xattr() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--xattr", "=value"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"invalid attribute "=value""#));
+    Ok(())
+}
@@ -1,12 +1,12 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
+use findr::{EntryType, FindQuery};
 use regex::{Regex, RegexBuilder};
 use std::{
     fs::{self, File},
     io::{self, BufRead, BufReader},
     mem,
 };
-use walkdir::WalkDir;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -122,11 +122,10 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<Result<String>> {
                 Ok(metadata) => {
                     if metadata.is_dir() {
                         if recursive {
-                            for entry in WalkDir::new(path)
-                                .into_iter()
-                                .flatten()
-                                .filter(|e| e.file_type().is_file())
-                            {
+                            let mut query = FindQuery::new()
+                                .path(path)
+                                .entry_type(EntryType::File);
+                            for entry in query.matches().flatten() {
                                 results.push(Ok(entry
                                     .path()
                                     .display()